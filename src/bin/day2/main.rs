@@ -1,12 +1,39 @@
-#![feature(iter_map_windows)]
-
-use std::{fs, str::FromStr};
+use std::{collections::VecDeque, env, fmt, fs, str::FromStr};
 
 use anyhow::{Error, Result};
 
 fn main() {
-    println!("part1: {}", part1());
-    println!("part2: {}", part2());
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("repair") => repair(&args[2..]),
+        _ => {
+            println!("part1: {}", part1());
+            println!("part2: {}", part2());
+            println!("edits to repair: {}", repair_edits());
+            println!("streamed with dampner: {}", stream_with_dampner());
+        }
+    }
+}
+
+// `cargo run --bin day2 -- repair [file]` prints every unsafe report in
+// `file` (input.txt by default) with the fewest edits that make it safe and
+// the levels after those edits.
+fn repair(args: &[String]) {
+    let input = fs::read_to_string(args.first().map_or("input.txt", String::as_str)).unwrap();
+    let rules = SafetyRules::default();
+    for (n, line) in input.lines().enumerate() {
+        let report: Report = line.parse().unwrap();
+        let repair = report.repair(&rules);
+        if repair.edits > 0 {
+            let levels: Vec<String> = repair.levels.iter().map(u32::to_string).collect();
+            println!(
+                "line {}: {line} -> {} ({} edits)",
+                n + 1,
+                levels.join(" "),
+                repair.edits
+            );
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -14,6 +41,131 @@ struct Report {
     levels: Vec<u32>,
 }
 
+// Which way the levels in a report are required to move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Trend {
+    Increasing,
+    Decreasing,
+    // Either direction, as long as every step agrees with the first
+    // non-plateau step.
+    Either,
+}
+
+// The rules a report must follow to be safe.
+#[derive(Debug, Clone)]
+struct SafetyRules {
+    min_step: u32,
+    max_step: u32,
+    trend: Trend,
+    allow_plateaus: bool,
+}
+
+impl Default for SafetyRules {
+    // The puzzle's rules: strictly monotonic, 1 to 3 apart.
+    fn default() -> Self {
+        Self {
+            min_step: 1,
+            max_step: 3,
+            trend: Trend::Either,
+            allow_plateaus: false,
+        }
+    }
+}
+
+impl SafetyRules {
+    // Returns true if stepping from level `from` to level `to` is allowed
+    // in a report moving in direction `trend` (which must not be Either).
+    fn allows_step(&self, from: u32, to: u32, trend: Trend) -> bool {
+        if from == to {
+            return self.allow_plateaus;
+        }
+        let step_trend = if to > from {
+            Trend::Increasing
+        } else {
+            Trend::Decreasing
+        };
+        let step = from.abs_diff(to);
+        step_trend == trend && (self.min_step..=self.max_step).contains(&step)
+    }
+
+    // Returns the fewest steps that safely get from level `from` to level
+    // `to` in direction `trend`, or `None` if no path of steps can.
+    fn bridge_steps(&self, from: u32, to: u32, trend: Trend) -> Option<u32> {
+        let distance = match trend {
+            Trend::Increasing => to.checked_sub(from)?,
+            Trend::Decreasing => from.checked_sub(to)?,
+            Trend::Either => unreachable!("bridge needs a concrete trend"),
+        };
+        if distance == 0 {
+            return self.allow_plateaus.then_some(1);
+        }
+        if self.max_step == 0 {
+            return None;
+        }
+        // A plateau is never a valid non-zero step.
+        let fewest = distance.div_ceil(self.max_step);
+        let most = distance / self.min_step.max(1);
+        (fewest <= most).then_some(fewest)
+    }
+
+    // Returns the levels strictly between `from` and `to` on the safe path
+    // of `steps` steps, where `steps` comes from `bridge_steps`.
+    fn bridge(&self, from: u32, to: u32, trend: Trend, steps: u32) -> Vec<u32> {
+        let distance = from.abs_diff(to);
+        let mut level = from;
+        // Spread the distance as evenly as possible over the steps.
+        (1..steps)
+            .map(|i| {
+                let step = distance / steps + u32::from(i <= distance % steps);
+                level = match trend {
+                    Trend::Increasing => level + step,
+                    _ => level - step,
+                };
+                level
+            })
+            .collect()
+    }
+
+    // The concrete directions a safe report may move in.
+    fn trends(&self) -> &'static [Trend] {
+        match self.trend {
+            Trend::Increasing => &[Trend::Increasing],
+            Trend::Decreasing => &[Trend::Decreasing],
+            Trend::Either => &[Trend::Increasing, Trend::Decreasing],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rule {
+    NoPlateaus,
+    MinStep,
+    MaxStep,
+    Trend,
+}
+
+// A rule broken by the step between two adjacent levels.
+#[derive(Debug, Clone, PartialEq)]
+struct Violation {
+    rule: Rule,
+    levels: (usize, usize),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (i, j) = self.levels;
+        write!(f, "{:?} rule broken between levels {i} and {j}", self.rule)
+    }
+}
+
+// A safe sequence of levels and the number of substitutions, insertions
+// and deletions it took to get there.
+#[derive(Debug, Clone, PartialEq)]
+struct Repair {
+    edits: usize,
+    levels: Vec<u32>,
+}
+
 impl FromStr for Report {
     type Err = Error;
 
@@ -28,45 +180,300 @@ impl FromStr for Report {
 }
 
 impl Report {
-    // Report is safe is all levels are monotonic, unique, and
-    // at most 3 apart.
+    // Report is safe if it breaks none of the default rules.
     fn is_safe(&self) -> bool {
-        let is_monotonic = self.levels.is_sorted() || self.levels.iter().rev().is_sorted();
-        let is_unique = self
-            .levels
-            .iter()
-            .map_windows(|&[x, y]| x == y)
-            .all(|same| !same);
-        let max_distance = self
-            .levels
-            .iter()
-            .map_windows(|&[x, y]| x.abs_diff(*y))
-            .max()
-            .expect("max distance");
-        is_monotonic && is_unique && max_distance <= 3
+        self.violations(&SafetyRules::default()).is_empty()
+    }
+
+    // Returns every rule broken by each pair of adjacent levels. Empty and
+    // single-level reports have no steps, so they never break a rule.
+    fn violations(&self, rules: &SafetyRules) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut trend = match rules.trend {
+            Trend::Either => None,
+            trend => Some(trend),
+        };
+        for (i, pair) in self.levels.windows(2).enumerate() {
+            let (from, to) = (pair[0], pair[1]);
+            let levels = (i, i + 1);
+            if from == to {
+                if !rules.allow_plateaus {
+                    violations.push(Violation {
+                        rule: Rule::NoPlateaus,
+                        levels,
+                    });
+                }
+                continue;
+            }
+            let step_trend = if to > from {
+                Trend::Increasing
+            } else {
+                Trend::Decreasing
+            };
+            if *trend.get_or_insert(step_trend) != step_trend {
+                violations.push(Violation {
+                    rule: Rule::Trend,
+                    levels,
+                });
+            }
+            let step = from.abs_diff(to);
+            if step < rules.min_step {
+                violations.push(Violation {
+                    rule: Rule::MinStep,
+                    levels,
+                });
+            }
+            if step > rules.max_step {
+                violations.push(Violation {
+                    rule: Rule::MaxStep,
+                    levels,
+                });
+            }
+        }
+        violations
     }
 
     // Report is safe with dampner if the report is safe with
     // at most one level removed.
     fn is_safe_with_dampner(&self) -> bool {
-        if self.is_safe() {
-            return true;
+        self.dampen(1, &SafetyRules::default()).is_some()
+    }
+
+    // Returns the indices of the fewest levels that need removing to make
+    // the report safe under `rules`, or `None` if that takes more than `k`
+    // removals.
+    //
+    // Runs in O(n * k) without cloning the levels: a safe chain ending at
+    // level i can only continue from one of the k + 1 levels before it,
+    // since every level skipped in between counts as a removal.
+    fn dampen(&self, k: usize, rules: &SafetyRules) -> Option<Vec<usize>> {
+        rules
+            .trends()
+            .iter()
+            .filter_map(|&trend| self.dampen_in_direction(k, rules, trend))
+            .min_by_key(|removed| removed.len())
+    }
+
+    // Finds the cheapest set of at most `k` removals that leaves a run of
+    // levels moving in direction `trend` under `rules`.
+    fn dampen_in_direction(
+        &self,
+        k: usize,
+        rules: &SafetyRules,
+        trend: Trend,
+    ) -> Option<Vec<usize>> {
+        let n = self.levels.len();
+        if n == 0 {
+            return Some(Vec::new());
         }
 
-        // Inefficient, but good enough for the input data.
-        self.levels.iter().enumerate().any(|(i, _)| {
-            let dampened = self.dampen_level(i);
-            dampened.is_safe()
-        })
+        // removals[i] is the fewest removals among levels 0..=i that leave a
+        // safe chain ending at (and keeping) level i, and previous[i] is the
+        // level kept before i on that chain.
+        let mut removals = vec![usize::MAX; n];
+        let mut previous = vec![None; n];
+        for i in 0..n {
+            if i <= k {
+                // Remove every level before i and start the chain here.
+                removals[i] = i;
+            }
+            for j in i.saturating_sub(k + 1)..i {
+                if removals[j] == usize::MAX
+                    || !rules.allows_step(self.levels[j], self.levels[i], trend)
+                {
+                    continue;
+                }
+                let cost = removals[j] + (i - j - 1);
+                if cost < removals[i] {
+                    removals[i] = cost;
+                    previous[i] = Some(j);
+                }
+            }
+        }
+
+        // Remove every level after the end of the chain.
+        let (cost, last) = (0..n)
+            .filter(|&i| removals[i] != usize::MAX)
+            .map(|i| (removals[i] + (n - 1 - i), i))
+            .min()?;
+        if cost > k {
+            return None;
+        }
+
+        let mut kept = vec![false; n];
+        let mut cursor = Some(last);
+        while let Some(i) = cursor {
+            kept[i] = true;
+            cursor = previous[i];
+        }
+        Some((0..n).filter(|&i| !kept[i]).collect())
     }
 
-    // Returns a copy of this Report with level `n` removed.
+    // Returns the safe sequence reachable from this report with the fewest
+    // level edits, where an edit substitutes, inserts or deletes one level.
     //
-    // Panics if `n` is not a level in `self`.
-    fn dampen_level(&self, n: usize) -> Self {
-        let mut dampened = self.levels.clone();
-        dampened.remove(n);
-        Self { levels: dampened }
+    // Every level that survives unedited belongs to a chain, and between two
+    // chained levels with m levels in between and a safe path of s steps,
+    // max(m, s - 1) edits are needed: substitute where possible, then
+    // delete or insert the difference. Levels before the first and after the
+    // last chained level are deleted.
+    fn repair(&self, rules: &SafetyRules) -> Repair {
+        rules
+            .trends()
+            .iter()
+            .map(|&trend| self.repair_in_direction(rules, trend))
+            .min_by_key(|repair| repair.edits)
+            .expect("at least one trend")
+    }
+
+    fn repair_in_direction(&self, rules: &SafetyRules, trend: Trend) -> Repair {
+        let n = self.levels.len();
+        if n == 0 {
+            return Repair {
+                edits: 0,
+                levels: Vec::new(),
+            };
+        }
+
+        // Edits and step count to bridge from level i to level j, if any.
+        let bridge = |i: usize, j: usize| {
+            let between = (j - i - 1) as u32;
+            let fewest = rules.bridge_steps(self.levels[i], self.levels[j], trend)?;
+            // Use no more steps than needed; if at most `between + 1` steps
+            // suffice, every in-between level is substituted or deleted.
+            Some((fewest.saturating_sub(1).max(between) as usize, fewest))
+        };
+
+        // edits[j] is the fewest edits to levels 0..=j that leave a safe
+        // sequence ending at (and keeping) level j, and previous[j] is the
+        // level kept before j with the number of steps bridging them.
+        let mut edits: Vec<usize> = (0..n).collect();
+        let mut previous = vec![None; n];
+        for j in 0..n {
+            for i in 0..j {
+                let Some((cost, steps)) = bridge(i, j) else {
+                    continue;
+                };
+                if edits[i] + cost < edits[j] {
+                    edits[j] = edits[i] + cost;
+                    previous[j] = Some((i, steps));
+                }
+            }
+        }
+
+        let (total, last) = (0..n).map(|j| (edits[j] + (n - 1 - j), j)).min().unwrap();
+
+        let mut levels = vec![self.levels[last]];
+        let mut cursor = last;
+        while let Some((i, steps)) = previous[cursor] {
+            let bridged = rules.bridge(self.levels[i], self.levels[cursor], trend, steps);
+            levels.extend(bridged.into_iter().rev());
+            levels.push(self.levels[i]);
+            cursor = i;
+        }
+        levels.reverse();
+
+        Repair {
+            edits: total,
+            levels,
+        }
+    }
+}
+
+// Where a stream of levels stands so far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Safe,
+    // Safe once the dampener removes some levels.
+    Dampened,
+    Unsafe,
+}
+
+// Emitted by SafetyMonitor at the level that first makes a stream unsafe
+// even with the dampener. No later level can make the stream safe again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Unrecoverable {
+    index: usize,
+}
+
+// Checks levels one at a time against the same rules as Report, keeping
+// only the last `k + 1` levels. After every level, its status matches
+// `Report::violations` and `Report::dampen` on everything seen so far.
+#[derive(Debug)]
+struct SafetyMonitor {
+    rules: SafetyRules,
+    k: usize,
+    seen: usize,
+    // The last k + 1 levels, each with the fewest removals leaving a safe
+    // chain that ends on it, per trend in `rules.trends()`.
+    recent: VecDeque<(u32, [usize; 2])>,
+    status: Status,
+}
+
+impl SafetyMonitor {
+    fn new(rules: SafetyRules, k: usize) -> Self {
+        Self {
+            rules,
+            k,
+            seen: 0,
+            recent: VecDeque::with_capacity(k + 1),
+            status: Status::Safe,
+        }
+    }
+
+    fn status(&self) -> Status {
+        self.status
+    }
+
+    // Feeds the next level in the stream. This is the same dynamic program
+    // as `Report::dampen`, but since a chain can only skip k levels, older
+    // levels are dropped.
+    fn push(&mut self, level: u32) -> Option<Unrecoverable> {
+        let index = self.seen;
+        self.seen += 1;
+        if self.status == Status::Unsafe {
+            return None;
+        }
+
+        let mut removals = [usize::MAX; 2];
+        for (t, &trend) in self.rules.trends().iter().enumerate() {
+            if index <= self.k {
+                removals[t] = index;
+            }
+            let first = index - self.recent.len();
+            for (j, (previous, previous_removals)) in (first..).zip(&self.recent) {
+                if previous_removals[t] == usize::MAX
+                    || !self.rules.allows_step(*previous, level, trend)
+                {
+                    continue;
+                }
+                removals[t] = removals[t].min(previous_removals[t] + (index - j - 1));
+            }
+        }
+        if self.recent.len() == self.k + 1 {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((level, removals));
+
+        // Remove every level after the end of the cheapest chain.
+        let first = self.seen - self.recent.len();
+        let fewest = (first..)
+            .zip(&self.recent)
+            .flat_map(|(j, (_, removals))| {
+                removals
+                    .iter()
+                    .filter(|&&r| r != usize::MAX)
+                    .map(move |r| r + (index - j))
+            })
+            .min()
+            .unwrap_or(usize::MAX);
+        self.status = match fewest {
+            0 => Status::Safe,
+            r if r <= self.k => Status::Dampened,
+            // Any chain through a later level costs at least as much.
+            _ => Status::Unsafe,
+        };
+        (self.status == Status::Unsafe).then_some(Unrecoverable { index })
     }
 }
 
@@ -87,3 +494,237 @@ fn part2() -> u32 {
         .filter(|r| r.is_safe_with_dampner())
         .count() as u32
 }
+
+fn repair_edits() -> usize {
+    let input = fs::read_to_string("input.txt").unwrap();
+    let rules = SafetyRules::default();
+    input
+        .lines()
+        .map(|line| line.parse::<Report>().unwrap())
+        .map(|r| r.repair(&rules).edits)
+        .sum()
+}
+
+// Same as part2, but feeding each report's levels through a SafetyMonitor.
+fn stream_with_dampner() -> u32 {
+    let input = fs::read_to_string("input.txt").unwrap();
+    input
+        .lines()
+        .filter(|line| {
+            let mut monitor = SafetyMonitor::new(SafetyRules::default(), 1);
+            line.split_ascii_whitespace()
+                .map(|n| n.parse().unwrap())
+                .all(|level| monitor.push(level).is_none())
+                && monitor.status() != Status::Unsafe
+        })
+        .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reports of up to `max_len` levels from 1 to 9, from a simple linear
+    // congruential generator so failures are reproducible.
+    fn reports(seed: u64, count: usize, max_len: usize) -> Vec<Report> {
+        let mut state = seed;
+        let mut next = |n: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % n
+        };
+        (0..count)
+            .map(|_| {
+                let len = next(max_len as u64 + 1) as usize;
+                Report {
+                    levels: (0..len).map(|_| next(9) as u32 + 1).collect(),
+                }
+            })
+            .collect()
+    }
+
+    fn rule_sets() -> Vec<SafetyRules> {
+        vec![
+            SafetyRules::default(),
+            SafetyRules {
+                trend: Trend::Increasing,
+                ..SafetyRules::default()
+            },
+            SafetyRules {
+                min_step: 2,
+                max_step: 5,
+                trend: Trend::Decreasing,
+                allow_plateaus: true,
+            },
+            SafetyRules {
+                allow_plateaus: true,
+                ..SafetyRules::default()
+            },
+        ]
+    }
+
+    fn is_safe_under(levels: &[u32], rules: &SafetyRules) -> bool {
+        Report {
+            levels: levels.to_vec(),
+        }
+        .violations(rules)
+        .is_empty()
+    }
+
+    // The fewest removals that make `report` safe, trying every subset of
+    // at most `k` levels.
+    fn brute_force_dampen(report: &Report, k: usize, rules: &SafetyRules) -> Option<usize> {
+        let n = report.levels.len();
+        (0u32..1 << n)
+            .filter(|removed| removed.count_ones() as usize <= k)
+            .filter(|removed| {
+                let kept: Vec<u32> = (0..n)
+                    .filter(|i| removed & 1 << i == 0)
+                    .map(|i| report.levels[i])
+                    .collect();
+                is_safe_under(&kept, rules)
+            })
+            .map(|removed| removed.count_ones() as usize)
+            .min()
+    }
+
+    #[test]
+    fn dampen_matches_brute_force() {
+        for rules in rule_sets() {
+            for report in reports(1, 2000, 8) {
+                for k in 0..=3 {
+                    let removed = report.dampen(k, &rules);
+                    assert_eq!(
+                        removed.as_ref().map(Vec::len),
+                        brute_force_dampen(&report, k, &rules),
+                        "{report:?} with k = {k} under {rules:?}"
+                    );
+                    if let Some(removed) = removed {
+                        let kept: Vec<u32> = (0..report.levels.len())
+                            .filter(|i| !removed.contains(i))
+                            .map(|i| report.levels[i])
+                            .collect();
+                        assert!(
+                            is_safe_under(&kept, &rules),
+                            "removing {removed:?} leaves {report:?} unsafe under {rules:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Every safe sequence of up to `max_len` levels from 0 to 10, built a
+    // level at a time since every prefix of a safe sequence is safe.
+    fn safe_sequences(rules: &SafetyRules, max_len: usize) -> Vec<Vec<u32>> {
+        let mut sequences = vec![Vec::new()];
+        let mut frontier = vec![Vec::new()];
+        for _ in 0..max_len {
+            let mut next = Vec::new();
+            for sequence in &frontier {
+                for level in 0..=10 {
+                    let mut longer = sequence.clone();
+                    longer.push(level);
+                    if is_safe_under(&longer, rules) {
+                        next.push(longer);
+                    }
+                }
+            }
+            sequences.extend(next.iter().cloned());
+            frontier = next;
+        }
+        sequences
+    }
+
+    // Substitutions, insertions and deletions to turn `a` into `b`.
+    fn edit_distance(a: &[u32], b: &[u32]) -> usize {
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, x) in a.iter().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, y) in b.iter().enumerate() {
+                let substitute = diagonal + usize::from(x != y);
+                diagonal = row[j + 1];
+                row[j + 1] = substitute.min(row[j] + 1).min(row[j + 1] + 1);
+            }
+        }
+        row[b.len()]
+    }
+
+    #[test]
+    fn repair_matches_exhaustive_search() {
+        let rule_sets = [
+            SafetyRules::default(),
+            SafetyRules {
+                min_step: 2,
+                max_step: 4,
+                trend: Trend::Increasing,
+                allow_plateaus: false,
+            },
+        ];
+        const MAX_LEN: usize = 5;
+        for rules in rule_sets {
+            // Repairs only bridge between kept levels, so they never need
+            // more than a couple of extra levels on reports this short.
+            let targets = safe_sequences(&rules, MAX_LEN + 2);
+            for report in reports(2, 300, MAX_LEN) {
+                let repair = report.repair(&rules);
+                let fewest = targets
+                    .iter()
+                    .map(|target| edit_distance(&report.levels, target))
+                    .min()
+                    .unwrap();
+                assert_eq!(repair.edits, fewest, "{report:?} under {rules:?}");
+                assert!(
+                    is_safe_under(&repair.levels, &rules),
+                    "{repair:?} for {report:?} is unsafe under {rules:?}"
+                );
+                assert_eq!(
+                    edit_distance(&report.levels, &repair.levels),
+                    repair.edits,
+                    "{repair:?} for {report:?} under {rules:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn monitor_agrees_with_report_on_every_prefix() {
+        for rules in rule_sets() {
+            for report in reports(3, 1000, 10) {
+                for k in 0..=3 {
+                    let mut monitor = SafetyMonitor::new(rules.clone(), k);
+                    let mut was_unsafe = false;
+                    for (i, &level) in report.levels.iter().enumerate() {
+                        let unrecoverable = monitor.push(level);
+                        let prefix = Report {
+                            levels: report.levels[..=i].to_vec(),
+                        };
+                        let expected = if prefix.violations(&rules).is_empty() {
+                            Status::Safe
+                        } else if prefix.dampen(k, &rules).is_some() {
+                            Status::Dampened
+                        } else {
+                            Status::Unsafe
+                        };
+                        assert_eq!(
+                            monitor.status(),
+                            expected,
+                            "{prefix:?} with k = {k} under {rules:?}"
+                        );
+                        // Only the level that first makes the stream unsafe
+                        // is reported.
+                        let first_unsafe = expected == Status::Unsafe && !was_unsafe;
+                        assert_eq!(
+                            unrecoverable,
+                            first_unsafe.then_some(Unrecoverable { index: i }),
+                            "{prefix:?} with k = {k} under {rules:?}"
+                        );
+                        was_unsafe |= expected == Status::Unsafe;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,44 +1,686 @@
-use std::fs;
+use std::{
+    env, fmt,
+    fs::File,
+    io::{self, ErrorKind, Read, Write},
+    ops::Range,
+    str::FromStr,
+};
 
-use regex::Regex;
+use anyhow::{anyhow, Error, Result};
 
 fn main() {
+    // `cargo run --bin day3 -- trace [file] [instructions]` traces `file`
+    // with a comma-separated list of BUILTINS, part2's set by default.
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("trace") {
+        let path = args.get(2).map_or("input.txt", String::as_str);
+        let instructions = args.get(3).map_or("mul,do,don't", String::as_str);
+        let input = File::open(path).unwrap();
+        let sum = Interpreter::new(instructions.parse().unwrap())
+            .trace(input, io::stdout().lock())
+            .unwrap();
+        println!("sum: {sum}");
+        return;
+    }
+
     println!("part1: {}", part1());
     println!("part2: {}", part2());
 }
 
-fn part1() -> i32 {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let re = Regex::new(r"mul\((?<d1>[0-9]{1,3}),(?<d2>[0-9]{1,3})\)").unwrap();
-    re.captures_iter(&input)
-        .map(|caps| {
-            let d1: i32 = caps.name("d1").unwrap().as_str().parse().unwrap();
-            let d2: i32 = caps.name("d2").unwrap().as_str().parse().unwrap();
-            d1 * d2
+// The most operands any instruction can take.
+const MAX_OPERANDS: usize = 4;
+
+// How an instruction is written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Syntax {
+    // `name(a,b,...)` with this many 1-3 digit operands.
+    Call(usize),
+    // Just `name`, such as a newline.
+    Bare,
+}
+
+// What an instruction does when it runs.
+#[derive(Debug, Clone, Copy)]
+enum Effect {
+    // Adds the result of applying the function to the operands to the sum,
+    // while instructions are enabled.
+    Value(fn(&[i32]) -> i32),
+    // Replaces the disabled depth with the result of the function.
+    Condition(fn(u32) -> u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Instruction {
+    name: &'static str,
+    syntax: Syntax,
+    effect: Effect,
+}
+
+impl Instruction {
+    const MUL: Self = Self::value("mul", |ops| ops[0] * ops[1]);
+    const ADD: Self = Self::value("add", |ops| ops[0] + ops[1]);
+    const SUB: Self = Self::value("sub", |ops| ops[0] - ops[1]);
+    const DO: Self = Self::condition("do", |_| 0);
+    const DONT: Self = Self::condition("don't", |_| 1);
+    const TOGGLE: Self = Self::condition("toggle", |depth| u32::from(depth == 0));
+    // Every `don't()` must be undone by its own `do()`.
+    const NESTED_DO: Self = Self::condition("do", |depth| depth.saturating_sub(1));
+    const NESTED_DONT: Self = Self::condition("don't", |depth| depth + 1);
+    // Re-enables instructions at the start of every line.
+    const NEWLINE: Self = Self {
+        name: "\n",
+        syntax: Syntax::Bare,
+        effect: Effect::Condition(|_| 0),
+    };
+
+    // `name(a,b)`.
+    const fn value(name: &'static str, f: fn(&[i32]) -> i32) -> Self {
+        Self {
+            name,
+            syntax: Syntax::Call(2),
+            effect: Effect::Value(f),
+        }
+    }
+
+    // `name()`.
+    const fn condition(name: &'static str, f: fn(u32) -> u32) -> Self {
+        Self {
+            name,
+            syntax: Syntax::Call(0),
+            effect: Effect::Condition(f),
+        }
+    }
+
+    // The longest text this instruction can match.
+    fn max_len(&self) -> usize {
+        match self.syntax {
+            // Each operand has up to 3 digits and is followed by a `,` or `)`.
+            Syntax::Call(arity) => self.name.len() + 1 + (arity * 4).max(1),
+            Syntax::Bare => self.name.len(),
+        }
+    }
+}
+
+// Instructions that can be picked by name for an InstructionSet.
+const BUILTINS: &[(&str, Instruction)] = &[
+    ("mul", Instruction::MUL),
+    ("add", Instruction::ADD),
+    ("sub", Instruction::SUB),
+    ("do", Instruction::DO),
+    ("don't", Instruction::DONT),
+    ("toggle", Instruction::TOGGLE),
+    ("nested-do", Instruction::NESTED_DO),
+    ("nested-don't", Instruction::NESTED_DONT),
+    ("newline", Instruction::NEWLINE),
+];
+
+// Conditional state shared by all instructions. Instructions are enabled
+// while the disabled depth is zero.
+#[derive(Debug, Default)]
+struct State {
+    disabled_depth: u32,
+}
+
+impl State {
+    fn enabled(&self) -> bool {
+        self.disabled_depth == 0
+    }
+}
+
+// The instructions an Interpreter understands.
+#[derive(Debug, Clone, Default)]
+struct InstructionSet {
+    instructions: Vec<Instruction>,
+    // Whether any instruction starts with each byte, to skip the rest fast.
+    first_bytes: Vec<bool>,
+}
+
+impl InstructionSet {
+    fn part1() -> Self {
+        Self::default().with(Instruction::MUL)
+    }
+
+    fn part2() -> Self {
+        Self::default()
+            .with(Instruction::MUL)
+            .with(Instruction::DO)
+            .with(Instruction::DONT)
+    }
+
+    // Adds an instruction.
+    //
+    // Panics if the name is empty or it takes more than MAX_OPERANDS.
+    fn with(mut self, instruction: Instruction) -> Self {
+        assert!(!instruction.name.is_empty(), "instruction needs a name");
+        if let Syntax::Call(arity) = instruction.syntax {
+            assert!(arity <= MAX_OPERANDS, "too many operands");
+        }
+        self.first_bytes.resize(256, false);
+        self.first_bytes[usize::from(instruction.name.as_bytes()[0])] = true;
+        self.instructions.push(instruction);
+        self
+    }
+
+    // The longest text any instruction, or near miss of one, can span.
+    fn max_len(&self) -> usize {
+        self.instructions
+            .iter()
+            .map(Instruction::max_len)
+            .max()
+            .unwrap_or(1)
+    }
+
+    // Runs `token` and returns what it adds to the sum.
+    fn execute(&self, token: &Token, state: &mut State) -> i32 {
+        match self.instructions[token.instruction].effect {
+            Effect::Value(f) if state.enabled() => f(token.operands()),
+            Effect::Value(_) => 0,
+            Effect::Condition(f) => {
+                state.disabled_depth = f(state.disabled_depth);
+                0
+            }
+        }
+    }
+}
+
+impl FromStr for InstructionSet {
+    type Err = Error;
+
+    // Parses a comma-separated list of BUILTINS names.
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',').try_fold(Self::default(), |set, name| {
+            let (_, instruction) = BUILTINS
+                .iter()
+                .find(|(builtin, _)| *builtin == name)
+                .ok_or_else(|| anyhow!("unknown instruction {name:?}"))?;
+            Ok(set.with(*instruction))
         })
-        .sum()
-}
-
-fn part2() -> i32 {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let re =
-        Regex::new(r"mul\((?<d1>[0-9]{1,3}),(?<d2>[0-9]{1,3})\)|(?<cond>do(n't)?\(\))").unwrap();
-    re.captures_iter(&input)
-        .scan(true, |enabled, captures| {
-            if let Some(condition) = captures.name("cond") {
-                match condition.as_str() {
-                    "do()" => *enabled = true,
-                    "don't()" => *enabled = false,
-                    _ => unreachable!(),
+    }
+}
+
+// An instruction from an InstructionSet and its operands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Token {
+    instruction: usize,
+    arity: usize,
+    operands: [i32; MAX_OPERANDS],
+}
+
+impl Token {
+    fn operands(&self) -> &[i32] {
+        &self.operands[..self.arity]
+    }
+}
+
+// Why something that starts like an instruction isn't one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rejection {
+    // Expected the given text but found the given byte, or end of input.
+    Expected(&'static str, Option<u8>),
+    // An operand with more than 3 digits.
+    LongOperand,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Expected(expected, Some(found)) => {
+                write!(f, "expected {expected}, found {:?}", char::from(*found))
+            }
+            Self::Expected(expected, None) => write!(f, "expected {expected}, found end of input"),
+            Self::LongOperand => write!(f, "operand has more than 3 digits"),
+        }
+    }
+}
+
+// A token, or a near miss that the grammar rejects, and where it is.
+struct Lexeme<'a> {
+    token: Result<Token, Rejection>,
+    span: Range<usize>,
+    // The bytes covered by `span`. For a near miss, this runs up to and
+    // including the offending byte.
+    text: &'a [u8],
+}
+
+// Splits corrupted memory into tokens, skipping everything that isn't a
+// well-formed instruction or a near miss of one.
+struct Lexer<'a> {
+    instructions: &'a InstructionSet,
+    input: &'a [u8],
+    pos: usize,
+    // Tokens are only looked for at positions before `stop`, though they
+    // may end after it.
+    stop: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(instructions: &'a InstructionSet, input: &'a [u8], stop: usize) -> Self {
+        Self {
+            instructions,
+            input,
+            pos: 0,
+            stop,
+        }
+    }
+
+    // Tries to read a token starting exactly at `start`, returning it (or
+    // why it was rejected) and the position just past what was examined.
+    // Returns `None` if no instruction's name starts at `start`.
+    //
+    // If several instructions' names match, the first to parse wins, and
+    // otherwise the near miss of the longest name is reported.
+    fn token_at(&self, start: usize) -> Option<(Result<Token, Rejection>, usize)> {
+        let rest = &self.input[start..];
+        let mut near_miss: Option<(usize, Rejection, usize)> = None;
+        for (i, instruction) in self.instructions.instructions.iter().enumerate() {
+            if !rest.starts_with(instruction.name.as_bytes()) {
+                continue;
+            }
+            let mut pos = start + instruction.name.len();
+            match self.operands_at(&mut pos, instruction.syntax) {
+                Ok((arity, operands)) => {
+                    let token = Token {
+                        instruction: i,
+                        arity,
+                        operands,
+                    };
+                    return Some((Ok(token), pos));
+                }
+                Err(rejection) => {
+                    let name_len = instruction.name.len();
+                    if near_miss.is_none_or(|(len, ..)| name_len > len) {
+                        near_miss = Some((name_len, rejection, pos));
+                    }
+                }
+            }
+        }
+        near_miss.map(|(_, rejection, pos)| (Err(rejection), pos))
+    }
+
+    // Reads the `(a,b,...)` that follows a name at `pos` and advances past it.
+    fn operands_at(
+        &self,
+        pos: &mut usize,
+        syntax: Syntax,
+    ) -> Result<(usize, [i32; MAX_OPERANDS]), Rejection> {
+        let mut operands = [0; MAX_OPERANDS];
+        let Syntax::Call(arity) = syntax else {
+            return Ok((0, operands));
+        };
+        self.expect_at(pos, b'(', "'('")?;
+        for (i, operand) in operands[..arity].iter_mut().enumerate() {
+            if i > 0 {
+                self.expect_at(pos, b',', "','")?;
+            }
+            *operand = self.number_at(pos)?;
+        }
+        self.expect_at(pos, b')', "')'")?;
+        Ok((arity, operands))
+    }
+
+    // Reads a 1-3 digit number at `pos` and advances past it.
+    fn number_at(&self, pos: &mut usize) -> Result<i32, Rejection> {
+        let digits = self.input[*pos..]
+            .iter()
+            .take(3)
+            .take_while(|b| b.is_ascii_digit())
+            .fold((0, 0), |(n, len), b| {
+                (n * 10 + i32::from(b - b'0'), len + 1)
+            });
+        match digits {
+            (_, 0) => {
+                let found = self.input.get(*pos).copied();
+                *pos = (*pos + 1).min(self.input.len());
+                Err(Rejection::Expected("a 1-3 digit operand", found))
+            }
+            (_, 3) if self.input.get(*pos + 3).is_some_and(u8::is_ascii_digit) => {
+                *pos += 4;
+                Err(Rejection::LongOperand)
+            }
+            (n, len) => {
+                *pos += len;
+                Ok(n)
+            }
+        }
+    }
+
+    // Consumes `byte` at `pos`, or advances past whatever is there instead.
+    fn expect_at(
+        &self,
+        pos: &mut usize,
+        byte: u8,
+        expected: &'static str,
+    ) -> Result<(), Rejection> {
+        let found = self.input.get(*pos).copied();
+        *pos = (*pos + 1).min(self.input.len());
+        if found == Some(byte) {
+            Ok(())
+        } else {
+            Err(Rejection::Expected(expected, found))
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Lexeme<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.stop {
+            let start = self.pos;
+            let first_byte = usize::from(self.input[start]);
+            if self.instructions.first_bytes.get(first_byte) == Some(&true) {
+                if let Some((token, end)) = self.token_at(start) {
+                    // Only a token is consumed; a near miss may overlap a
+                    // real instruction, as in `mul(mul(2,3)`.
+                    self.pos = if token.is_ok() { end } else { start + 1 };
+                    return Some(Lexeme {
+                        token,
+                        span: start..end,
+                        text: &self.input[start..end],
+                    });
                 }
-                Some(0)
-            } else if *enabled {
-                let d1: i32 = captures.name("d1").unwrap().as_str().parse().unwrap();
-                let d2: i32 = captures.name("d2").unwrap().as_str().parse().unwrap();
-                Some(d1 * d2)
+            }
+            self.pos += 1;
+        }
+        None
+    }
+}
+
+// Lexes any reader in fixed-size chunks, so memory use doesn't grow with
+// the size of the input and the bytes needn't be valid UTF-8.
+struct Scanner<'a, R: Read> {
+    instructions: &'a InstructionSet,
+    reader: R,
+    chunk_size: usize,
+}
+
+impl<'a, R: Read> Scanner<'a, R> {
+    const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+    fn new(instructions: &'a InstructionSet, reader: R) -> Self {
+        Self::with_chunk_size(instructions, reader, Self::DEFAULT_CHUNK_SIZE)
+    }
+
+    fn with_chunk_size(instructions: &'a InstructionSet, reader: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        Self {
+            instructions,
+            reader,
+            chunk_size,
+        }
+    }
+
+    // Calls `f` with every lexeme in the whole input, with spans counted
+    // from the start of the input.
+    //
+    // A token can straddle two chunks, so positions within the last
+    // `max_len - 1` bytes of a chunk aren't lexed until the next chunk has
+    // been read in behind them.
+    fn scan(mut self, mut f: impl FnMut(Lexeme<'_>)) -> io::Result<()> {
+        let max_len = self.instructions.max_len();
+        let mut buf = vec![0; self.chunk_size + max_len - 1];
+        // Bytes carried over from the previous chunk, and the offset of the
+        // start of `buf` in the whole input.
+        let mut carried = 0;
+        let mut offset = 0;
+        loop {
+            let read = match self.reader.read(&mut buf[carried..]) {
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let end = carried + read;
+            let stop = if read == 0 {
+                end
             } else {
-                Some(0)
+                end.saturating_sub(max_len - 1)
+            };
+
+            let mut lexer = Lexer::new(self.instructions, &buf[..end], stop);
+            for lexeme in lexer.by_ref() {
+                let span = lexeme.span;
+                f(Lexeme {
+                    span: offset + span.start..offset + span.end,
+                    ..lexeme
+                });
             }
-        })
-        .sum()
+            if read == 0 {
+                return Ok(());
+            }
+
+            let resume = lexer.pos;
+            buf.copy_within(resume..end, 0);
+            carried = end - resume;
+            offset += resume;
+        }
+    }
+}
+
+// Runs the instructions found in corrupted memory and sums what they add.
+struct Interpreter {
+    instructions: InstructionSet,
+    state: State,
+    // Wide enough for the products of a multi-gigabyte dump.
+    sum: i64,
+}
+
+impl Interpreter {
+    fn new(instructions: InstructionSet) -> Self {
+        Self {
+            instructions,
+            state: State::default(),
+            sum: 0,
+        }
+    }
+
+    fn run(mut self, reader: impl Read) -> io::Result<i64> {
+        let Self {
+            instructions,
+            state,
+            sum,
+        } = &mut self;
+        Scanner::new(instructions, reader).scan(|lexeme| {
+            if let Ok(token) = lexeme.token {
+                *sum += i64::from(instructions.execute(&token, state));
+            }
+        })?;
+        Ok(self.sum)
+    }
+
+    // Writes a line for every instruction and near miss to `out`, by byte
+    // offset, and returns the sum.
+    fn trace(mut self, reader: impl Read, mut out: impl Write) -> io::Result<i64> {
+        let Self {
+            instructions,
+            state,
+            sum,
+        } = &mut self;
+        let mut result = Ok(());
+        Scanner::new(instructions, reader).scan(|lexeme| {
+            if result.is_err() {
+                return;
+            }
+            let offset = lexeme.span.start;
+            let text = String::from_utf8_lossy(lexeme.text).replace('\n', "\\n");
+            result = match lexeme.token {
+                Ok(token) => {
+                    let added = instructions.execute(&token, state);
+                    *sum += i64::from(added);
+                    let enabled = if state.enabled() {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    };
+                    match token.operands() {
+                        [] => writeln!(out, "{offset:>8}  {text:<14}{enabled}"),
+                        operands => writeln!(
+                            out,
+                            "{offset:>8}  {text:<14}{operands:?}, {enabled}, {added:+}"
+                        ),
+                    }
+                }
+                Err(rejection) => {
+                    writeln!(out, "{offset:>8}  {text:<14}rejected: {rejection}")
+                }
+            };
+        })?;
+        result?;
+        Ok(self.sum)
+    }
+}
+
+fn part1() -> i64 {
+    let input = File::open("input.txt").unwrap();
+    Interpreter::new(InstructionSet::part1())
+        .run(input)
+        .unwrap()
+}
+
+fn part2() -> i64 {
+    let input = File::open("input.txt").unwrap();
+    Interpreter::new(InstructionSet::part2())
+        .run(input)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    // The original regex solution to part1, kept to check the lexer against.
+    fn regex_part1(input: &str) -> i64 {
+        let re = Regex::new(r"mul\((?<d1>[0-9]{1,3}),(?<d2>[0-9]{1,3})\)").unwrap();
+        re.captures_iter(input)
+            .map(|caps| {
+                let d1: i64 = caps.name("d1").unwrap().as_str().parse().unwrap();
+                let d2: i64 = caps.name("d2").unwrap().as_str().parse().unwrap();
+                d1 * d2
+            })
+            .sum()
+    }
+
+    // The original regex solution to part2.
+    fn regex_part2(input: &str) -> i64 {
+        let re = Regex::new(r"mul\((?<d1>[0-9]{1,3}),(?<d2>[0-9]{1,3})\)|(?<cond>do(n't)?\(\))")
+            .unwrap();
+        re.captures_iter(input)
+            .scan(true, |enabled, captures| {
+                if let Some(condition) = captures.name("cond") {
+                    *enabled = condition.as_str() == "do()";
+                    Some(0)
+                } else if *enabled {
+                    let d1: i64 = captures.name("d1").unwrap().as_str().parse().unwrap();
+                    let d2: i64 = captures.name("d2").unwrap().as_str().parse().unwrap();
+                    Some(d1 * d2)
+                } else {
+                    Some(0)
+                }
+            })
+            .sum()
+    }
+
+    fn run(instructions: InstructionSet, input: &str) -> i64 {
+        Interpreter::new(instructions)
+            .run(input.as_bytes())
+            .unwrap()
+    }
+
+    // Builds a corrupted string out of instruction fragments, near misses
+    // and noise, from a simple linear congruential generator so failures
+    // are reproducible.
+    fn corrupted(seed: u64, pieces: usize) -> String {
+        const PIECES: [&str; 20] = [
+            "mul(",
+            "mul",
+            "(",
+            ")",
+            ",",
+            "do()",
+            "don't()",
+            "do(",
+            "don't",
+            "1",
+            "22",
+            "333",
+            "4444",
+            "mul(1,2)",
+            "mul(999,999)",
+            "mul (2,3)",
+            "mul[3,7]",
+            "x",
+            "\n",
+            "mmul(",
+        ];
+        let mut state = seed;
+        let mut s = String::new();
+        for _ in 0..pieces {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            s.push_str(PIECES[(state >> 33) as usize % PIECES.len()]);
+        }
+        s
+    }
+
+    // Lexes `input` in chunks of `chunk_size` bytes, keeping every lexeme's
+    // span and token.
+    fn scan(
+        instructions: &InstructionSet,
+        input: &str,
+        chunk_size: usize,
+    ) -> Vec<(Range<usize>, Result<Token, Rejection>)> {
+        let mut lexemes = Vec::new();
+        Scanner::with_chunk_size(instructions, input.as_bytes(), chunk_size)
+            .scan(|lexeme| lexemes.push((lexeme.span, lexeme.token)))
+            .unwrap();
+        lexemes
+    }
+
+    #[test]
+    fn chunk_boundaries_change_nothing() {
+        let instructions = InstructionSet::part2().with(Instruction::ADD);
+        for seed in 0..20 {
+            let input = corrupted(seed, 100);
+            let whole = scan(&instructions, &input, input.len().max(1));
+            for chunk_size in 1..=64 {
+                assert_eq!(
+                    scan(&instructions, &input, chunk_size),
+                    whole,
+                    "chunk size {chunk_size} differs on {input:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sum_does_not_overflow() {
+        let input = "mul(999,999)".repeat(10_000);
+        assert_eq!(run(InstructionSet::part1(), &input), 998_001 * 10_000);
+    }
+
+    #[test]
+    fn matches_regex_on_example() {
+        let input = include_str!("example.txt");
+        assert_eq!(run(InstructionSet::part1(), input), regex_part1(input));
+        assert_eq!(run(InstructionSet::part2(), input), regex_part2(input));
+    }
+
+    #[test]
+    fn matches_regex_on_corrupted_input() {
+        for seed in 0..500 {
+            let input = corrupted(seed, 200);
+            assert_eq!(
+                run(InstructionSet::part1(), &input),
+                regex_part1(&input),
+                "part1 differs on {input:?}"
+            );
+            assert_eq!(
+                run(InstructionSet::part2(), &input),
+                regex_part2(&input),
+                "part2 differs on {input:?}"
+            );
+        }
+    }
 }
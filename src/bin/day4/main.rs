@@ -1,112 +1,979 @@
-use std::fs;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env, fs,
+    hint::black_box,
+    io::{self, IsTerminal},
+    str::FromStr,
+    time::Instant,
+};
+
+use anyhow::{bail, Error, Result};
 
 fn main() {
-    println!("part1: {}", part1());
-    println!("part2: {}", part2());
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("words") => words(&args[2..]),
+        Some("render") => render(&args[2..]),
+        Some("bench") => bench(&args[2..]),
+        Some("search") => search(&args[2..]),
+        _ => {
+            println!("part1: {}", part1());
+            println!("part2: {}", part2());
+        }
+    }
 }
 
-fn part1() -> i32 {
+// `cargo run --bin day4 -- words <word list> [grid]` counts every word in
+// the list, one per line, found in the grid (input.txt by default).
+fn words(args: &[String]) {
+    let words = fs::read_to_string(&args[0]).unwrap();
+    let dictionary = Dictionary::new(words.lines().map(str::trim).filter(|w| !w.is_empty()));
+    let grid = fs::read_to_string(args.get(1).map_or("input.txt", String::as_str)).unwrap();
+    let search: WordSearch = grid.parse().unwrap();
+    let mut counts = vec![0; dictionary.words.len()];
+    for (word, _) in search.find_words(&dictionary) {
+        counts[word] += 1;
+    }
+    for (word, count) in dictionary.words.iter().zip(counts) {
+        if count > 0 {
+            println!("{word}: {count}");
+        }
+    }
+}
+
+// `cargo run --bin day4 -- render [xmas|x-mas] [grid]` prints the grid
+// (example.txt by default) with part1's or part2's matches highlighted.
+fn render(args: &[String]) {
+    let grid = fs::read_to_string(args.get(1).map_or("example.txt", String::as_str)).unwrap();
+    let search: WordSearch = grid.parse().unwrap();
+    let matches: Vec<Vec<(usize, usize)>> = match args.first().map_or("xmas", String::as_str) {
+        "xmas" => search
+            .find("XMAS", ALL_DIRECTIONS)
+            .iter()
+            .map(|m| search.cells(m, 4))
+            .collect(),
+        "x-mas" => {
+            let x_mas: Pattern = X_MAS.parse().unwrap();
+            search
+                .find_pattern(&x_mas)
+                .iter()
+                .map(|m| m.cells(&x_mas))
+                .collect()
+        }
+        other => panic!("unknown pattern {other:?}, expected xmas or x-mas"),
+    };
+    let colour = io::stdout().is_terminal();
+    print!("{}", search.render(&matches, colour));
+    println!("matches: {}", matches.len());
+}
+
+// `cargo run --bin day4 -- search <word> [line|torus|path] [grid]` lists
+// every occurrence of `word` in the grid (example.txt by default) reading in
+// straight lines, straight lines that wrap around the edges, or any chain
+// of adjacent cells.
+fn search(args: &[String]) {
+    let word = &args[0];
+    let grid = fs::read_to_string(args.get(2).map_or("example.txt", String::as_str)).unwrap();
+    let search: WordSearch = grid.parse().unwrap();
+    let len = word.chars().count();
+    let paths: Vec<Vec<(usize, usize)>> = match args.get(1).map_or("line", String::as_str) {
+        "line" => search
+            .find(word, ALL_DIRECTIONS)
+            .iter()
+            .map(|m| search.cells(m, len))
+            .collect(),
+        "torus" => {
+            let search = search.clone().wrapping();
+            search
+                .find(word, ALL_DIRECTIONS)
+                .iter()
+                .map(|m| search.cells(m, len))
+                .collect()
+        }
+        "path" => search.find_paths(word),
+        other => panic!("unknown mode {other:?}, expected line, torus or path"),
+    };
+    for path in &paths {
+        println!("{path:?}");
+    }
+    println!("count: {}", paths.len());
+}
+
+// `cargo run --release --bin day4 -- bench [iterations]` times both parts
+// on input.txt with WordSearch and with ByteGrid, parsing included.
+fn bench(args: &[String]) {
+    let iterations: u32 = args.first().map_or(100, |n| n.parse().unwrap());
     let input = fs::read_to_string("input.txt").unwrap();
-    // Create a vector of vectors of characters.
-    let p: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+    let x_mas: Pattern = X_MAS.parse().unwrap();
 
-    let nrows = p.len();
-    let ncols = p.first().unwrap().len();
+    let time = |name: &str, f: &dyn Fn() -> usize| {
+        let start = Instant::now();
+        let mut result = 0;
+        for _ in 0..iterations {
+            result = black_box(f());
+        }
+        println!(
+            "{name:<20} {result:>6} {:>10.2?}/iter",
+            start.elapsed() / iterations
+        );
+        result
+    };
 
-    let mut count = 0;
+    let words = time("part1 WordSearch", &|| {
+        let search: WordSearch = input.parse().unwrap();
+        search.count("XMAS", ALL_DIRECTIONS)
+    });
+    let bytes = time("part1 ByteGrid", &|| {
+        ByteGrid::new(&input).unwrap().count_xmas()
+    });
+    assert_eq!(words, bytes);
 
-    // Let i be the row index and j be the column index.
-    for i in 0..nrows {
-        for j in 0..ncols {
-            if p[i][j] != 'X' {
-                continue;
+    let words = time("part2 WordSearch", &|| {
+        let search: WordSearch = input.parse().unwrap();
+        search.count_pattern(&x_mas)
+    });
+    let bytes = time("part2 ByteGrid", &|| {
+        ByteGrid::new(&input).unwrap().count_x_mas()
+    });
+    assert_eq!(words, bytes);
+}
+
+// The eight directions a word can read in, clockwise from 12 o'clock, and
+// ordered that way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Direction {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+const ALL_DIRECTIONS: &[Direction] = &[
+    Direction::Up,
+    Direction::UpRight,
+    Direction::Right,
+    Direction::DownRight,
+    Direction::Down,
+    Direction::DownLeft,
+    Direction::Left,
+    Direction::UpLeft,
+];
+
+impl Direction {
+    // The change in (row, col) for one step in this direction.
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::UpRight => (-1, 1),
+            Direction::Right => (0, 1),
+            Direction::DownRight => (1, 1),
+            Direction::Down => (1, 0),
+            Direction::DownLeft => (1, -1),
+            Direction::Left => (0, -1),
+            Direction::UpLeft => (-1, -1),
+        }
+    }
+
+    fn reverse(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::Right => Direction::Left,
+            Direction::DownRight => Direction::UpLeft,
+            Direction::Down => Direction::Up,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::Left => Direction::Right,
+            Direction::UpLeft => Direction::DownRight,
+        }
+    }
+}
+
+// Where a word was found: the position of its first letter and the
+// direction it reads in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Match {
+    row: usize,
+    col: usize,
+    direction: Direction,
+}
+
+// Two MAS in the shape of an X, in any orientation.
+const X_MAS: &str = "\
+M.S
+.A.
+M.S";
+
+// One of the 8 rotations and reflections of a square.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Transform {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+    Transpose,
+    AntiTranspose,
+}
+
+const ALL_TRANSFORMS: &[Transform] = &[
+    Transform::Identity,
+    Transform::Rotate90,
+    Transform::Rotate180,
+    Transform::Rotate270,
+    Transform::FlipHorizontal,
+    Transform::FlipVertical,
+    Transform::Transpose,
+    Transform::AntiTranspose,
+];
+
+impl Transform {
+    // Where (row, col) in a `rows` x `cols` grid ends up. Rotations are
+    // clockwise.
+    fn apply(self, row: usize, col: usize, rows: usize, cols: usize) -> (usize, usize) {
+        let (last_row, last_col) = (rows - 1, cols - 1);
+        match self {
+            Transform::Identity => (row, col),
+            Transform::Rotate90 => (col, last_row - row),
+            Transform::Rotate180 => (last_row - row, last_col - col),
+            Transform::Rotate270 => (last_col - col, row),
+            Transform::FlipHorizontal => (row, last_col - col),
+            Transform::FlipVertical => (last_row - row, col),
+            Transform::Transpose => (col, row),
+            Transform::AntiTranspose => (last_col - col, last_row - row),
+        }
+    }
+}
+
+// A small grid of letters to match, where `.` matches any letter.
+#[derive(Debug, Clone)]
+struct Pattern {
+    // The literal cells as (row, col, letter), relative to the top left of
+    // the smallest box holding them all.
+    cells: Vec<(usize, usize, char)>,
+    rows: usize,
+    cols: usize,
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let cells: Vec<(usize, usize, char)> = s
+            .lines()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c != '.')
+                    .map(move |(col, c)| (row, col, c))
+            })
+            .collect();
+        if cells.is_empty() {
+            bail!("pattern needs at least one letter");
+        }
+        Ok(Self::normalized(cells))
+    }
+}
+
+impl Pattern {
+    // Builds a pattern from literal cells, shifted to the top left and in
+    // row-major order.
+    fn normalized(mut cells: Vec<(usize, usize, char)>) -> Self {
+        let min_row = cells.iter().map(|&(r, _, _)| r).min().unwrap_or(0);
+        let min_col = cells.iter().map(|&(_, c, _)| c).min().unwrap_or(0);
+        for (r, c, _) in cells.iter_mut() {
+            *r -= min_row;
+            *c -= min_col;
+        }
+        cells.sort_unstable();
+        let rows = cells.iter().map(|&(r, _, _)| r + 1).max().unwrap_or(0);
+        let cols = cells.iter().map(|&(_, c, _)| c + 1).max().unwrap_or(0);
+        Self { cells, rows, cols }
+    }
+
+    fn transformed(&self, transform: Transform) -> Self {
+        Self::normalized(
+            self.cells
+                .iter()
+                .map(|&(r, c, letter)| {
+                    let (r, c) = transform.apply(r, c, self.rows, self.cols);
+                    (r, c, letter)
+                })
+                .collect(),
+        )
+    }
+
+    // Returns each distinct orientation of this pattern, with the first
+    // transform that produces it.
+    fn orientations(&self) -> Vec<(Transform, Pattern)> {
+        let mut orientations: Vec<(Transform, Pattern)> = Vec::new();
+        for &transform in ALL_TRANSFORMS {
+            let pattern = self.transformed(transform);
+            if !orientations.iter().any(|(_, p)| p.cells == pattern.cells) {
+                orientations.push((transform, pattern));
+            }
+        }
+        orientations
+    }
+}
+
+// Where a pattern was found: the top left of its box in the grid and the
+// transform of the pattern that matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PatternMatch {
+    row: usize,
+    col: usize,
+    transform: Transform,
+}
+
+impl PatternMatch {
+    // The cells covered by the letters of `pattern` found here.
+    fn cells(&self, pattern: &Pattern) -> Vec<(usize, usize)> {
+        pattern
+            .transformed(self.transform)
+            .cells
+            .iter()
+            .map(|&(r, c, _)| (self.row + r, self.col + c))
+            .collect()
+    }
+}
+
+// A set of words to look for all at once, as an Aho-Corasick automaton
+// over their letters.
+#[derive(Debug)]
+struct Dictionary {
+    words: Vec<String>,
+    // Trie of the words' letters, where node 0 is the root.
+    children: Vec<HashMap<char, usize>>,
+    // The node for the longest proper suffix of each node that's also in
+    // the trie.
+    suffix: Vec<usize>,
+    // Indices of the words ending at each node, including via suffixes.
+    outputs: Vec<Vec<usize>>,
+}
+
+impl Dictionary {
+    // Builds a dictionary from `words`, ignoring empty words and repeats.
+    fn new<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut dictionary = Self {
+            words: Vec::new(),
+            children: vec![HashMap::new()],
+            suffix: vec![0],
+            outputs: vec![Vec::new()],
+        };
+        for word in words {
+            dictionary.insert(word);
+        }
+        dictionary.link_suffixes();
+        dictionary
+    }
+
+    fn insert(&mut self, word: &str) {
+        if word.is_empty() {
+            return;
+        }
+        let mut node = 0;
+        for letter in word.chars() {
+            node = match self.children[node].get(&letter) {
+                Some(&child) => child,
+                None => {
+                    let child = self.children.len();
+                    self.children.push(HashMap::new());
+                    self.suffix.push(0);
+                    self.outputs.push(Vec::new());
+                    self.children[node].insert(letter, child);
+                    child
+                }
+            };
+        }
+        // Suffix links aren't filled in yet, so a word already ends here only
+        // if it's this one.
+        if !self.outputs[node].is_empty() {
+            return;
+        }
+        self.outputs[node].push(self.words.len());
+        self.words.push(word.to_string());
+    }
+
+    // Fills in suffix links breadth first, so every shorter node is linked
+    // before the nodes that depend on it.
+    fn link_suffixes(&mut self) {
+        let mut queue: VecDeque<usize> = self.children[0].values().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let children: Vec<(char, usize)> =
+                self.children[node].iter().map(|(&l, &c)| (l, c)).collect();
+            for (letter, child) in children {
+                let suffix = self.next(self.suffix[node], letter);
+                self.suffix[child] = suffix;
+                let inherited = self.outputs[suffix].clone();
+                self.outputs[child].extend(inherited);
+                queue.push_back(child);
+            }
+        }
+    }
+
+    // The node reached by reading `letter` at `node`.
+    fn next(&self, mut node: usize, letter: char) -> usize {
+        loop {
+            if let Some(&child) = self.children[node].get(&letter) {
+                return child;
+            }
+            if node == 0 {
+                return 0;
+            }
+            node = self.suffix[node];
+        }
+    }
+}
+
+// A rectangular grid of letters.
+#[derive(Debug, Clone)]
+struct WordSearch {
+    grid: Vec<Vec<char>>,
+    rows: usize,
+    cols: usize,
+    // Whether the grid is a torus, so stepping off one edge comes back on
+    // at the opposite edge.
+    wrap: bool,
+}
+
+impl FromStr for WordSearch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let grid: Vec<Vec<char>> = s.lines().map(|line| line.chars().collect()).collect();
+        let rows = grid.len();
+        let cols = grid.first().map_or(0, Vec::len);
+        if grid.iter().any(|row| row.len() != cols) {
+            bail!("rows must all be {cols} letters long");
+        }
+        Ok(Self {
+            grid,
+            rows,
+            cols,
+            wrap: false,
+        })
+    }
+}
+
+impl WordSearch {
+    // Treats the grid as a torus, so words can cross its edges. Doesn't
+    // affect `find_pattern` or `find_words`.
+    fn wrapping(self) -> Self {
+        Self { wrap: true, ..self }
+    }
+
+    // Returns the position `steps` steps from (row, col) in `direction`, or
+    // `None` if that's off the grid.
+    fn position(
+        &self,
+        row: usize,
+        col: usize,
+        direction: Direction,
+        steps: usize,
+    ) -> Option<(usize, usize)> {
+        let (dr, dc) = direction.delta();
+        let (dr, dc) = (dr * steps as isize, dc * steps as isize);
+        if self.wrap {
+            let row = (row as isize + dr).rem_euclid(self.rows as isize);
+            let col = (col as isize + dc).rem_euclid(self.cols as isize);
+            return Some((row as usize, col as usize));
+        }
+        let row = row.checked_add_signed(dr).filter(|&r| r < self.rows)?;
+        let col = col.checked_add_signed(dc).filter(|&c| c < self.cols)?;
+        Some((row, col))
+    }
+
+    // Returns the letter `steps` steps from (row, col) in `direction`, or
+    // `None` if that's off the grid.
+    fn step(&self, row: usize, col: usize, direction: Direction, steps: usize) -> Option<char> {
+        let (row, col) = self.position(row, col, direction, steps)?;
+        Some(self.grid[row][col])
+    }
+
+    // The cells covered by a word of `len` letters matched at `m`.
+    fn cells(&self, m: &Match, len: usize) -> Vec<(usize, usize)> {
+        (0..len)
+            .map(|i| {
+                self.position(m.row, m.col, m.direction, i)
+                    .expect("in grid")
+            })
+            .collect()
+    }
+
+    // Returns every occurrence of `word` reading in one of `directions`.
+    //
+    // A palindrome reads the same in opposite directions, and a single
+    // letter the same in all of them, so each set of cells is only matched
+    // once, in whichever of its directions comes first in ALL_DIRECTIONS.
+    fn find(&self, word: &str, directions: &[Direction]) -> Vec<Match> {
+        let letters: Vec<char> = word.chars().collect();
+        let Some(&first) = letters.first() else {
+            return Vec::new();
+        };
+        let is_palindrome = letters.iter().eq(letters.iter().rev());
+        let mut unique_directions: Vec<Direction> = Vec::new();
+        for &direction in ALL_DIRECTIONS.iter().filter(|d| directions.contains(d)) {
+            let same_cells_as_earlier = if letters.len() == 1 {
+                !unique_directions.is_empty()
+            } else {
+                is_palindrome && unique_directions.contains(&direction.reverse())
+            };
+            if !same_cells_as_earlier {
+                unique_directions.push(direction);
             }
-            // Found an X. Search the clock, starting at 12 o'clock,
-            // then 1:30, 3, 4:30, 6, 7:30, 9, and lastly 10:30.
-            // I will call these positions u (up), r (right), l (left),
-            // d (down) and ur, dr, dl, ul for the 4 diagonals. The name
-            // is the direction the clock arrow is pointing.
-            //
-            // First, eliminate search paths if they are too close to an edge.
-            let u = i >= 3;
-            let r = j < ncols - 3;
-            let d = i < nrows - 3;
-            let l = j >= 3;
-            let ur = u && r;
-            let ul = u && l;
-            let dr = d && r;
-            let dl = d && l;
+        }
 
-            // Look up: dec i
-            if u && p[i - 1][j] == 'M' && p[i - 2][j] == 'A' && p[i - 3][j] == 'S' {
-                count += 1;
+        let mut matches = Vec::new();
+        // The cells of every match so far, when wrapping.
+        let mut seen: HashSet<Vec<(usize, usize)>> = HashSet::new();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if self.grid[row][col] != first {
+                    continue;
+                }
+                for &direction in &unique_directions {
+                    let found = (1..letters.len())
+                        .all(|i| self.step(row, col, direction, i) == Some(letters[i]));
+                    if !found {
+                        continue;
+                    }
+                    let m = Match {
+                        row,
+                        col,
+                        direction,
+                    };
+                    if self.wrap {
+                        // On a small torus a word can run back over its own
+                        // cells, and different directions can cover the
+                        // same cells, so drop those matches.
+                        let mut cells = self.cells(&m, letters.len());
+                        let mut distinct = cells.clone();
+                        distinct.sort_unstable();
+                        distinct.dedup();
+                        if distinct.len() < cells.len() {
+                            continue;
+                        }
+                        if is_palindrome {
+                            let reversed: Vec<(usize, usize)> =
+                                cells.iter().rev().copied().collect();
+                            cells = cells.min(reversed);
+                        }
+                        if !seen.insert(cells) {
+                            continue;
+                        }
+                    }
+                    matches.push(m);
+                }
             }
-            // Look up and right: dec i, inc j
-            if ur && p[i - 1][j + 1] == 'M' && p[i - 2][j + 2] == 'A' && p[i - 3][j + 3] == 'S' {
-                count += 1;
+        }
+        matches
+    }
+
+    // Counts the occurrences of `word` reading in one of `directions`.
+    fn count(&self, word: &str, directions: &[Direction]) -> usize {
+        self.find(word, directions).len()
+    }
+
+    // Returns every path spelling `word` through a chain of adjacent cells,
+    // in any of the eight directions at each step, without reusing a cell.
+    // A palindrome's path and its reverse are only returned once.
+    fn find_paths(&self, word: &str) -> Vec<Vec<(usize, usize)>> {
+        let letters: Vec<char> = word.chars().collect();
+        let Some(&first) = letters.first() else {
+            return Vec::new();
+        };
+        let mut paths = Vec::new();
+        let mut path = Vec::new();
+        let mut visited = vec![vec![false; self.cols]; self.rows];
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if self.grid[row][col] == first {
+                    self.extend_path(&letters, (row, col), &mut path, &mut visited, &mut paths);
+                }
             }
-            // Look right: inc j
-            if r && p[i][j + 1] == 'M' && p[i][j + 2] == 'A' && p[i][j + 3] == 'S' {
-                count += 1;
+        }
+        if letters.iter().eq(letters.iter().rev()) {
+            paths.retain(|path| path.iter().le(path.iter().rev()));
+        }
+        paths
+    }
+
+    // Adds `cell` to `path` and tries every way of spelling the rest of
+    // `letters` from there, pushing complete paths onto `paths`.
+    fn extend_path(
+        &self,
+        letters: &[char],
+        cell: (usize, usize),
+        path: &mut Vec<(usize, usize)>,
+        visited: &mut [Vec<bool>],
+        paths: &mut Vec<Vec<(usize, usize)>>,
+    ) {
+        path.push(cell);
+        visited[cell.0][cell.1] = true;
+        if path.len() == letters.len() {
+            paths.push(path.clone());
+        } else {
+            // On a small torus, several directions can reach the same cell.
+            let mut neighbours: Vec<(usize, usize)> = ALL_DIRECTIONS
+                .iter()
+                .filter_map(|&d| self.position(cell.0, cell.1, d, 1))
+                .collect();
+            neighbours.sort_unstable();
+            neighbours.dedup();
+            for (row, col) in neighbours {
+                if !visited[row][col] && self.grid[row][col] == letters[path.len()] {
+                    self.extend_path(letters, (row, col), path, visited, paths);
+                }
             }
-            // Look down and right: inc i, inc j
-            if dr && p[i + 1][j + 1] == 'M' && p[i + 2][j + 2] == 'A' && p[i + 3][j + 3] == 'S' {
-                count += 1;
+        }
+        visited[cell.0][cell.1] = false;
+        path.pop();
+    }
+
+    // Returns every place `pattern` matches in any rotation or reflection.
+    // Orientations that look the same, like the 180 degree rotation of a
+    // symmetric pattern, only match once.
+    fn find_pattern(&self, pattern: &Pattern) -> Vec<PatternMatch> {
+        let mut matches = Vec::new();
+        for (transform, oriented) in pattern.orientations() {
+            if oriented.rows > self.rows || oriented.cols > self.cols {
+                continue;
             }
-            // Look down: inc i
-            if d && p[i + 1][j] == 'M' && p[i + 2][j] == 'A' && p[i + 3][j] == 'S' {
-                count += 1;
+            for row in 0..=self.rows - oriented.rows {
+                for col in 0..=self.cols - oriented.cols {
+                    let found = oriented
+                        .cells
+                        .iter()
+                        .all(|&(r, c, letter)| self.grid[row + r][col + c] == letter);
+                    if found {
+                        matches.push(PatternMatch {
+                            row,
+                            col,
+                            transform,
+                        });
+                    }
+                }
             }
-            // Look down and left: inc i, dec j
-            if dl && p[i + 1][j - 1] == 'M' && p[i + 2][j - 2] == 'A' && p[i + 3][j - 3] == 'S' {
-                count += 1;
+        }
+        matches
+    }
+
+    fn count_pattern(&self, pattern: &Pattern) -> usize {
+        self.find_pattern(pattern).len()
+    }
+
+    // Draws the grid with only the letters in `matches` showing, as in the
+    // puzzle's examples. With `colour`, every letter shows instead, and
+    // each match is picked out in ANSI colour, cycling through six.
+    fn render(&self, matches: &[Vec<(usize, usize)>], colour: bool) -> String {
+        const COLOURS: [u8; 6] = [31, 32, 33, 34, 35, 36];
+        // The match each cell shows, if any. Later matches draw over
+        // earlier ones.
+        let mut shown: Vec<Vec<Option<usize>>> = vec![vec![None; self.cols]; self.rows];
+        for (i, cells) in matches.iter().enumerate() {
+            for &(row, col) in cells {
+                shown[row][col] = Some(i);
             }
-            // Look left: dec j
-            if l && p[i][j - 1] == 'M' && p[i][j - 2] == 'A' && p[i][j - 3] == 'S' {
-                count += 1;
+        }
+
+        let mut out = String::new();
+        for (letters, shown) in self.grid.iter().zip(shown) {
+            for (&letter, shown) in letters.iter().zip(shown) {
+                match (shown, colour) {
+                    (Some(i), true) => {
+                        let code = COLOURS[i % COLOURS.len()];
+                        out.push_str(&format!("\x1b[1;{code}m{letter}\x1b[0m"));
+                    }
+                    (None, true) => out.push_str(&format!("\x1b[2m{letter}\x1b[0m")),
+                    (Some(_), false) => out.push(letter),
+                    (None, false) => out.push('.'),
+                }
             }
-            // Look up and left: dec i, dec j
-            if ul && p[i - 1][j - 1] == 'M' && p[i - 2][j - 2] == 'A' && p[i - 3][j - 3] == 'S' {
-                count += 1;
+            out.push('\n');
+        }
+        out
+    }
+
+    // Returns the cells of every straight line through the grid reading in
+    // `direction`, from the edge it enters at to the edge it leaves by.
+    fn lines(&self, direction: Direction) -> Vec<Vec<(usize, usize)>> {
+        let (dr, dc) = direction.delta();
+        let in_grid = |row: Option<usize>, col: Option<usize>| {
+            row.is_some_and(|r| r < self.rows) && col.is_some_and(|c| c < self.cols)
+        };
+        let mut lines = Vec::new();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if in_grid(row.checked_add_signed(-dr), col.checked_add_signed(-dc)) {
+                    continue;
+                }
+                let mut line = Vec::new();
+                let (mut r, mut c) = (Some(row), Some(col));
+                while let (true, Some(row), Some(col)) = (in_grid(r, c), r, c) {
+                    line.push((row, col));
+                    (r, c) = (row.checked_add_signed(dr), col.checked_add_signed(dc));
+                }
+                lines.push(line);
             }
         }
+        lines
     }
 
-    count
+    // Returns every occurrence of every word in `dictionary`, in all eight
+    // directions, as (word index, match). Each line is read once each way.
+    //
+    // As with `find`, a palindrome is only matched in whichever of its two
+    // directions comes first in ALL_DIRECTIONS, and a single letter only
+    // reading up.
+    fn find_words(&self, dictionary: &Dictionary) -> Vec<(usize, Match)> {
+        let lengths: Vec<usize> = dictionary.words.iter().map(|w| w.chars().count()).collect();
+        let palindromes: Vec<bool> = dictionary
+            .words
+            .iter()
+            .map(|w| w.chars().eq(w.chars().rev()))
+            .collect();
+
+        let mut matches = Vec::new();
+        let forwards = [
+            Direction::Right,
+            Direction::Down,
+            Direction::DownRight,
+            Direction::DownLeft,
+        ];
+        for forward in forwards {
+            for line in self.lines(forward) {
+                let reversed: Vec<(usize, usize)> = line.iter().rev().copied().collect();
+                for (direction, cells) in [(forward, &line), (forward.reverse(), &reversed)] {
+                    let mut node = 0;
+                    for (end, &(row, col)) in cells.iter().enumerate() {
+                        node = dictionary.next(node, self.grid[row][col]);
+                        for &word in &dictionary.outputs[node] {
+                            let repeat = if lengths[word] == 1 {
+                                direction != Direction::Up
+                            } else {
+                                palindromes[word] && direction > direction.reverse()
+                            };
+                            if repeat {
+                                continue;
+                            }
+                            let (row, col) = cells[end + 1 - lengths[word]];
+                            matches.push((
+                                word,
+                                Match {
+                                    row,
+                                    col,
+                                    direction,
+                                },
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        matches
+    }
 }
 
-fn part2() -> i32 {
-    let input = fs::read_to_string("input.txt").unwrap();
+// The puzzle input searched in place: rows of bytes with a line ending
+// after each, so the cell at (row, col) is byte `row * stride + col`.
+// Anchor letters are found with memchr rather than by visiting every
+// cell, and each direction is a fixed offset into the bytes.
+struct ByteGrid<'a> {
+    bytes: &'a [u8],
+    rows: usize,
+    cols: usize,
+    stride: usize,
+}
 
-    // Create a vector of vectors of characters.
-    let p: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+impl<'a> ByteGrid<'a> {
+    fn new(input: &'a str) -> Result<Self> {
+        let bytes = input.as_bytes();
+        let rows = input.lines().count();
+        let cols = input.lines().next().map_or(0, str::len);
+        let stride = memchr::memchr(b'\n', bytes).map_or(bytes.len(), |i| i + 1);
+        let line_ending = &bytes[cols..stride];
+        for (row, line) in input.lines().enumerate() {
+            let start = row * stride;
+            let ending = bytes.get(start + cols..(start + stride).min(bytes.len()));
+            let last = row + 1 == rows;
+            if line.len() != cols
+                || bytes.get(start..start + cols) != Some(line.as_bytes())
+                || !(ending == Some(line_ending) || last && ending == Some(b""))
+            {
+                bail!("row {row} must be {cols} letters and end like the first row");
+            }
+        }
+        Ok(Self {
+            bytes,
+            rows,
+            cols,
+            stride,
+        })
+    }
 
-    let nrows = p.len();
-    let ncols = p.first().unwrap().len();
+    // Counts XMAS reading in all eight directions.
+    fn count_xmas(&self) -> usize {
+        let stride = self.stride as isize;
+        let mut count = 0;
+        for i in memchr::memchr_iter(b'X', self.bytes) {
+            let (row, col) = (i / self.stride, i % self.stride);
+            let up = row >= 3;
+            let down = row + 3 < self.rows;
+            let left = col >= 3;
+            let right = col + 3 < self.cols;
+            let directions = [
+                (up, -stride),
+                (up && right, 1 - stride),
+                (right, 1),
+                (down && right, stride + 1),
+                (down, stride),
+                (down && left, stride - 1),
+                (left, -1),
+                (up && left, -stride - 1),
+            ];
+            count += directions
+                .into_iter()
+                .filter(|&(fits, step)| fits && self.reads_mas(i, step))
+                .count();
+        }
+        count
+    }
 
-    let mut count = 0;
+    // Returns true if MAS follows byte `i`, `step` bytes apart.
+    fn reads_mas(&self, i: usize, step: isize) -> bool {
+        let at = |k: isize| self.bytes[i.wrapping_add_signed(k * step)];
+        at(1) == b'M' && at(2) == b'A' && at(3) == b'S'
+    }
 
-    // Let i be the row index and j be the column index.
-    for i in 1..nrows - 1 {
-        for j in 1..ncols - 1 {
-            if p[i][j] != 'A' {
-                continue;
-            }
+    // Counts two MAS crossing in an X at an A.
+    fn count_x_mas(&self) -> usize {
+        let stride = self.stride;
+        memchr::memchr_iter(b'A', self.bytes)
+            .filter(|&i| {
+                let (row, col) = (i / stride, i % stride);
+                if row == 0 || row + 1 >= self.rows || col == 0 || col + 1 >= self.cols {
+                    return false;
+                }
+                let is_mas = |a: u8, b: u8| matches!((a, b), (b'M', b'S') | (b'S', b'M'));
+                let ul = self.bytes[i - stride - 1];
+                let ur = self.bytes[i - stride + 1];
+                let dl = self.bytes[i + stride - 1];
+                let dr = self.bytes[i + stride + 1];
+                is_mas(ul, dr) && is_mas(ur, dl)
+            })
+            .count()
+    }
+}
 
-            // Found an A.
-            let ul = p[i - 1][j - 1];
-            let ur = p[i - 1][j + 1];
-            let dl = p[i + 1][j - 1];
-            let dr = p[i + 1][j + 1];
+fn part1() -> i32 {
+    let input = fs::read_to_string("input.txt").unwrap();
+    let search: WordSearch = input.parse().unwrap();
+    search.count("XMAS", ALL_DIRECTIONS) as i32
+}
+
+fn part2() -> i32 {
+    let input = fs::read_to_string("input.txt").unwrap();
+    let search: WordSearch = input.parse().unwrap();
+    search.count_pattern(&X_MAS.parse().unwrap()) as i32
+}
 
-            let mas_one = (ul == 'M' && dr == 'S') || (ul == 'S' && dr == 'M');
-            let mas_two = (ur == 'M' && dl == 'S') || (ur == 'S' && dl == 'M');
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            if mas_one && mas_two {
-                count += 1;
+    // Grids of up to 6x6 letters from `alphabet`, from a simple linear
+    // congruential generator so failures are reproducible.
+    fn grids(seed: u64, count: usize, alphabet: &[char]) -> Vec<WordSearch> {
+        let mut state = seed;
+        let mut next = |n: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % n
+        };
+        (0..count)
+            .map(|_| {
+                let (rows, cols) = (next(6) + 1, next(6) + 1);
+                let grid: Vec<String> = (0..rows)
+                    .map(|_| {
+                        (0..cols)
+                            .map(|_| alphabet[next(alphabet.len() as u64) as usize])
+                            .collect()
+                    })
+                    .collect();
+                grid.join("\n").parse().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn find_words_matches_find() {
+        let words = ["A", "B", "AB", "ABA", "BAB", "AA", "ABBA", "XMAS", "SAMX"];
+        let dictionary = Dictionary::new(words);
+        let mut searches = grids(1, 200, &['A', 'B', 'X']);
+        searches.push(include_str!("example.txt").parse().unwrap());
+        for search in searches {
+            let mut found: Vec<HashSet<Match>> = vec![HashSet::new(); words.len()];
+            for (word, m) in search.find_words(&dictionary) {
+                assert!(found[word].insert(m), "{m:?} repeated in {search:?}");
+            }
+            for (word, found) in words.iter().zip(found) {
+                let expected: HashSet<Match> =
+                    search.find(word, ALL_DIRECTIONS).into_iter().collect();
+                assert_eq!(found, expected, "{word} in {search:?}");
             }
         }
     }
 
-    count
+    fn torus(grid: &str) -> WordSearch {
+        grid.parse::<WordSearch>().unwrap().wrapping()
+    }
+
+    #[test]
+    fn torus_matches_never_reuse_a_cell() {
+        assert_eq!(torus("AB").find("ABAB", ALL_DIRECTIONS), []);
+        assert_eq!(torus("A").find("AA", ALL_DIRECTIONS), []);
+    }
+
+    #[test]
+    fn torus_matches_each_cell_sequence_once() {
+        // On a single row that wraps, every direction but Up and Down steps
+        // from (0, 0) to (0, 1), so only the first of them is kept.
+        assert_eq!(
+            torus("AB").find("AB", ALL_DIRECTIONS),
+            [Match {
+                row: 0,
+                col: 0,
+                direction: Direction::UpRight,
+            }]
+        );
+        // A palindrome read backwards covers the same cells.
+        assert_eq!(torus("ABA").count("ABA", ALL_DIRECTIONS), 1);
+        assert_eq!(torus("A").count("A", ALL_DIRECTIONS), 1);
+    }
+
+    #[test]
+    fn paths_never_reuse_a_cell() {
+        let search: WordSearch = "AB".parse().unwrap();
+        assert_eq!(search.find_paths("AB"), [vec![(0, 0), (0, 1)]]);
+        assert!(search.find_paths("ABA").is_empty());
+        assert!(search.find_paths("ABAB").is_empty());
+        assert!(torus("AB").find_paths("ABA").is_empty());
+    }
+
+    #[test]
+    fn paths_return_a_palindrome_once() {
+        let search: WordSearch = "ABA".parse().unwrap();
+        assert_eq!(search.find_paths("ABA"), [vec![(0, 0), (0, 1), (0, 2)]]);
+        // Both ways round the square spell ABBA, in either direction.
+        let search: WordSearch = "AB\nBA".parse().unwrap();
+        assert_eq!(search.find_paths("ABBA").len(), 2);
+        assert_eq!(search.find_paths("AB").len(), 4);
+    }
 }
@@ -1,113 +1,653 @@
-use anyhow::{Error, Result};
+use anyhow::{bail, Context, Error, Result};
 use std::{
-    collections::{HashMap, HashSet},
-    fs,
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    env, fmt, fs, io, process,
     str::FromStr,
 };
 
 fn main() {
-    println!("part1: {}", part1());
-    println!("part2: {}", part2());
+    let mut args: Vec<String> = env::args().collect();
+    // `--middle lower|upper|error` picks the middle page of even-length
+    // updates; by default they're rejected.
+    let mut middle_policy = MiddlePolicy::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--middle") {
+        let policy = args.get(i + 1).expect("--middle needs a policy");
+        middle_policy = policy.parse().unwrap();
+        args.drain(i..i + 2);
+    }
+
+    match args.get(1).map(String::as_str) {
+        Some("analyze") => analyze(&args[2..], middle_policy),
+        Some("orders") => orders(&args[2..], middle_policy),
+        Some("count") => count(&args[2..], middle_policy),
+        Some("list") => list(&args[2..], middle_policy),
+        Some("dot") => dot(&args[2..], middle_policy),
+        Some("rules") => rules(&args[2..]),
+        _ => {
+            println!("part1: {}", part1(middle_policy));
+            println!("part2: {}", part2(middle_policy));
+        }
+    }
 }
 
-fn part1() -> i32 {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let (page_ordering_rules, pages_to_update) = input.split_once("\n\n").unwrap();
-    let mut ordering_rules: HashMap<i32, HashSet<i32>> = HashMap::new();
-    for rule in page_ordering_rules.lines() {
-        let (s1, s2) = rule.split_once('|').unwrap();
-        let n1 = s1.parse().expect("number");
-        let n2 = s2.parse().expect("number");
-        ordering_rules
-            .entry(n1)
-            .and_modify(|set| _ = set.insert(n2))
-            .or_insert(HashSet::from([n2]));
-    }
-    let updates = pages_to_update
-        .lines()
-        .map(|line| Update::from_str(line).unwrap());
-    let mut sum = 0;
-    let empty_hashset = HashSet::new();
-    for update in updates {
-        let pages = update.pages;
-        let mut correct = true;
-        for (i, page) in pages.iter().enumerate() {
-            let earlier_pages: HashSet<i32> = HashSet::from_iter(pages[..i].iter().cloned());
-            let later_pages = ordering_rules.get(page).unwrap_or(&empty_hashset);
-            if !earlier_pages.is_disjoint(later_pages) {
-                correct = false;
-                break;
-            }
+// `cargo run --bin day5 -- analyze [file]` checks the rules in `file`
+// (input.txt by default) and explains every invalid update.
+fn analyze(args: &[String], middle_policy: MiddlePolicy) {
+    let input = fs::read_to_string(args.first().map_or("input.txt", String::as_str)).unwrap();
+    let queue = PrintQueue::parse(&input, middle_policy).unwrap();
+    print!("{}", Analysis::new(&queue));
+}
+
+// `cargo run --bin day5 -- orders [file]` sorts every update and says
+// whether the rules leave only one way to order it.
+fn orders(args: &[String], middle_policy: MiddlePolicy) {
+    let input = fs::read_to_string(args.first().map_or("input.txt", String::as_str)).unwrap();
+    let queue = PrintQueue::parse(&input, middle_policy).unwrap();
+    for (i, update) in queue.updates.iter().enumerate() {
+        match queue.sort_by_rules(&update.pages) {
+            Ok(order) => println!("update {i}: {order:?}"),
+            Err(err) => println!("update {i}: {err}"),
         }
-        if correct {
-            let middle_page = pages[pages.len() / 2];
-            sum += middle_page;
+    }
+}
+
+// `cargo run --bin day5 -- count [file] [cap]` counts the orders the rules
+// allow for every update, stopping at `cap` (1000 by default).
+fn count(args: &[String], middle_policy: MiddlePolicy) {
+    let input = fs::read_to_string(args.first().map_or("input.txt", String::as_str)).unwrap();
+    let cap = args.get(1).map_or(1000, |cap| cap.parse().unwrap());
+    let queue = PrintQueue::parse(&input, middle_policy).unwrap();
+    for (i, update) in queue.updates.iter().enumerate() {
+        match queue.count_orders(&update.pages, cap) {
+            Ok(count) if count == cap => println!("update {i}: at least {count} orders"),
+            Ok(1) => println!("update {i}: 1 order"),
+            Ok(count) => println!("update {i}: {count} orders"),
+            Err(err) => println!("update {i}: {err}"),
         }
     }
+}
 
-    sum
+// `cargo run --bin day5 -- list <update> [file] [limit]` prints the first
+// `limit` (10 by default) orders the rules allow for update number
+// `update`.
+fn list(args: &[String], middle_policy: MiddlePolicy) {
+    let update: usize = args[0].parse().unwrap();
+    let input = fs::read_to_string(args.get(1).map_or("input.txt", String::as_str)).unwrap();
+    let limit = args.get(2).map_or(10, |limit| limit.parse().unwrap());
+    let queue = PrintQueue::parse(&input, middle_policy).unwrap();
+    for order in queue.orders(&queue.updates[update].pages).take(limit) {
+        println!("{order:?}");
+    }
+}
+
+// `cargo run --bin day5 -- dot [file] [update] [--highlight] [--reduce]`
+// prints the rules as a Graphviz graph, only between the pages of update
+// number `update` if given. `--highlight` colours broken rules red and
+// `--reduce` leaves out rules that follow from others.
+fn dot(args: &[String], middle_policy: MiddlePolicy) {
+    let (flags, args): (Vec<&String>, Vec<&String>) =
+        args.iter().partition(|arg| arg.starts_with("--"));
+    let options = DotOptions {
+        update: args.get(1).map(|update| update.parse().unwrap()),
+        highlight: flags.iter().any(|&flag| flag == "--highlight"),
+        reduce: flags.iter().any(|&flag| flag == "--reduce"),
+    };
+    let input = fs::read_to_string(args.first().map_or("input.txt", |file| file.as_str())).unwrap();
+    let queue = PrintQueue::parse(&input, middle_policy).unwrap();
+    match queue.to_dot(&options) {
+        Ok(dot) => print!("{dot}"),
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            process::exit(1);
+        }
+    }
 }
 
-fn part2() -> i32 {
+// `cargo run --bin day5 -- rules [file]` feeds a RuleSet one line at a
+// time from `file` (stdin by default): `a|b` or `+a|b` adds a rule, `-a|b`
+// removes one, and a comma-separated update asks whether it's valid. A
+// puzzle input can be fed in, but if its rules form cycles as a whole, as
+// input.txt's do, the rules closing them are rejected and the updates are
+// checked without them.
+fn rules(args: &[String]) {
+    let input = match args.first() {
+        Some(file) => fs::read_to_string(file).unwrap(),
+        None => io::read_to_string(io::stdin()).unwrap(),
+    };
+    let mut rule_set = RuleSet::default();
+    for line in input.lines().filter(|line| !line.is_empty()) {
+        let (remove, rule) = match line.strip_prefix('-') {
+            Some(rule) => (true, rule),
+            None => (false, line.strip_prefix('+').unwrap_or(line)),
+        };
+        if let Some((before, after)) = rule.split_once('|') {
+            let (before, after) = (before.parse().unwrap(), after.parse().unwrap());
+            if remove {
+                if !rule_set.remove(before, after) {
+                    println!("{rule}: no such rule");
+                }
+            } else if let Err(cycle) = rule_set.add(before, after) {
+                println!("{rule} rejected: {cycle}");
+            }
+        } else {
+            let update: Update = line.parse().unwrap();
+            let valid = if rule_set.is_valid(&update.pages) {
+                "valid"
+            } else {
+                "invalid"
+            };
+            println!("{line}: {valid}");
+        }
+    }
+}
+
+fn part1(middle_policy: MiddlePolicy) -> i32 {
     let input = fs::read_to_string("input.txt").unwrap();
-    let (page_ordering_rules, pages_to_update) = input.split_once("\n\n").unwrap();
-    let mut ordering_rules: HashMap<i32, HashSet<i32>> = HashMap::new();
-    for rule in page_ordering_rules.lines() {
-        let (s1, s2) = rule.split_once('|').unwrap();
-        let n1 = s1.parse().expect("number");
-        let n2 = s2.parse().expect("number");
-        ordering_rules
-            .entry(n1)
-            .and_modify(|set| _ = set.insert(n2))
-            .or_insert(HashSet::from([n2]));
-    }
-    let updates = pages_to_update
-        .lines()
-        .map(|line| Update::from_str(line).unwrap());
-    let mut sum = 0;
-    let empty_hashset = HashSet::new();
-    for update in updates {
-        let mut pages = update.pages;
-        let mut correct = true;
-        for (i, page) in pages.iter().enumerate() {
-            let earlier_pages: HashSet<i32> = HashSet::from_iter(pages[..i].iter().cloned());
-            let later_pages = ordering_rules.get(page).unwrap_or(&empty_hashset);
-            if !earlier_pages.is_disjoint(later_pages) {
-                correct = false;
+    let queue = PrintQueue::parse(&input, middle_policy).unwrap();
+    queue.ordered_middle_pages()
+}
+
+fn part2(middle_policy: MiddlePolicy) -> i32 {
+    let input = fs::read_to_string("input.txt").unwrap();
+    let queue = PrintQueue::parse(&input, middle_policy).unwrap();
+    let (sum, unsorted) = queue.reordered_middle_pages();
+    for (i, err) in unsorted {
+        eprintln!("update {i} left out: {err}");
+    }
+    sum
+}
+
+// Which page counts as the middle of an update with an even number of
+// pages.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum MiddlePolicy {
+    // The earlier of the two middle pages.
+    Lower,
+    // The later of the two middle pages.
+    Upper,
+    // Reject updates with an even number of pages when parsing.
+    #[default]
+    Error,
+}
+
+impl FromStr for MiddlePolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lower" => Ok(Self::Lower),
+            "upper" => Ok(Self::Upper),
+            "error" => Ok(Self::Error),
+            _ => bail!("unknown middle policy {s:?}, expected lower, upper or error"),
+        }
+    }
+}
+
+// Page ordering rules and the updates to print under them.
+#[derive(Debug, Clone)]
+struct PrintQueue {
+    // The pages that must come after each page.
+    ordering_rules: HashMap<i32, HashSet<i32>>,
+    updates: Vec<Update>,
+    middle_policy: MiddlePolicy,
+}
+
+impl FromStr for PrintQueue {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s, MiddlePolicy::default())
+    }
+}
+
+impl PrintQueue {
+    // Parses rules of the form `a|b` with `a` and `b` different pages, then
+    // a blank line, then updates of comma-separated pages, with
+    // `middle_policy` for even-length updates.
+    fn parse(s: &str, middle_policy: MiddlePolicy) -> Result<Self> {
+        let mut lines = s.lines().enumerate();
+        let mut ordering_rules: HashMap<i32, HashSet<i32>> = HashMap::new();
+        for (n, rule) in lines.by_ref() {
+            if rule.is_empty() {
                 break;
             }
+            let (before, after) = rule
+                .split_once('|')
+                .with_context(|| format!("line {}: expected a rule like 1|2", n + 1))?;
+            let before = before
+                .parse()
+                .with_context(|| format!("line {}: bad page {before:?}", n + 1))?;
+            let after = after
+                .parse()
+                .with_context(|| format!("line {}: bad page {after:?}", n + 1))?;
+            if before == after {
+                bail!("line {}: page {before} can't come before itself", n + 1);
+            }
+            ordering_rules.entry(before).or_default().insert(after);
         }
-        if !correct {
-            // Must sort the updates.
-            while !correct {
-                correct = true;
-                for (i, page) in pages.iter().enumerate() {
-                    let earlier_pages: HashSet<i32> =
-                        HashSet::from_iter(pages[..i].iter().cloned());
-                    let later_pages = ordering_rules.get(page).unwrap_or(&empty_hashset);
-                    if !earlier_pages.is_disjoint(later_pages) {
-                        correct = false;
-                        // Sort
-                        let mut j = 0;
-                        for (page_idx, earlier_page) in pages[..i].iter().enumerate() {
-                            if later_pages.contains(earlier_page) {
-                                j = page_idx;
-                                break;
-                            }
+
+        let mut updates = Vec::new();
+        for (n, line) in lines {
+            let update: Update = line
+                .parse()
+                .with_context(|| format!("line {}: bad update", n + 1))?;
+            if middle_policy == MiddlePolicy::Error && update.pages.len().is_multiple_of(2) {
+                bail!(
+                    "line {}: update has {} pages, so no single middle page",
+                    n + 1,
+                    update.pages.len()
+                );
+            }
+            updates.push(update);
+        }
+
+        Ok(Self {
+            ordering_rules,
+            updates,
+            middle_policy,
+        })
+    }
+
+    // Returns true if `pages` breaks none of the rules.
+    fn is_ordered(&self, pages: &[i32]) -> bool {
+        let empty_hashset = HashSet::new();
+        let mut earlier_pages: HashSet<i32> = HashSet::new();
+        for page in pages {
+            let later_pages = self.ordering_rules.get(page).unwrap_or(&empty_hashset);
+            if !earlier_pages.is_disjoint(later_pages) {
+                return false;
+            }
+            earlier_pages.insert(*page);
+        }
+        true
+    }
+
+    fn middle_page(&self, pages: &[i32]) -> i32 {
+        pages[self.middle_index(pages.len())]
+    }
+
+    fn middle_index(&self, len: usize) -> usize {
+        match self.middle_policy {
+            MiddlePolicy::Lower => (len - 1) / 2,
+            MiddlePolicy::Upper | MiddlePolicy::Error => len / 2,
+        }
+    }
+
+    // Sums the middle pages of the updates that are already in order.
+    fn ordered_middle_pages(&self) -> i32 {
+        self.updates
+            .iter()
+            .filter(|update| self.is_ordered(&update.pages))
+            .map(|update| self.middle_page(&update.pages))
+            .sum()
+    }
+
+    // Sums the middle pages of the updates that are out of order, once
+    // they've been put in order. An update the rules allow in several
+    // orders still counts if they all share a middle page. Updates with no
+    // order, or whose orders differ in the middle, are left out of the sum
+    // and returned by index with the reason.
+    fn reordered_middle_pages(&self) -> (i32, Vec<(usize, OrderError)>) {
+        let mut sum = 0;
+        let mut unsorted = Vec::new();
+        for (i, update) in self.updates.iter().enumerate() {
+            if self.is_ordered(&update.pages) {
+                continue;
+            }
+            match self.sort_by_rules(&update.pages) {
+                Ok(order) => sum += self.middle_page(&order),
+                Err(OrderError::Ambiguous(order, _))
+                    if self.is_pinned(&order, self.middle_index(order.len())) =>
+                {
+                    sum += self.middle_page(&order)
+                }
+                Err(err) => unsorted.push((i, err)),
+            }
+        }
+        (sum, unsorted)
+    }
+
+    // Returns true if every valid order of `order`'s pages has the same
+    // page as `order` at position `i`, where `order` is itself valid. A
+    // page can be anywhere from after all the pages the rules chain before
+    // it to before all the pages they chain after it, so it's pinned to
+    // `i` only if the rules chain it after every page before `i` and
+    // before every page after.
+    fn is_pinned(&self, order: &[i32], i: usize) -> bool {
+        let chained = |forwards: bool| {
+            let mut seen = vec![false; order.len()];
+            let mut stack = vec![i];
+            while let Some(current) = stack.pop() {
+                for (next, &page) in order.iter().enumerate() {
+                    let rule = if forwards {
+                        self.has_rule(order[current], page)
+                    } else {
+                        self.has_rule(page, order[current])
+                    };
+                    if rule && !seen[next] {
+                        seen[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            seen.into_iter().filter(|&seen| seen).count()
+        };
+        chained(false) == i && chained(true) == order.len() - 1 - i
+    }
+
+    // Counts the orders of `pages` that follow every rule, up to `cap`.
+    // Works through the sets of pages that can be printed first, each
+    // counted once, so it's limited to MAX_COUNTED_PAGES pages.
+    fn count_orders(&self, pages: &[i32], cap: u64) -> Result<u64> {
+        if pages.len() > MAX_COUNTED_PAGES {
+            bail!(
+                "can't count orders of {} pages, at most {MAX_COUNTED_PAGES}",
+                pages.len()
+            );
+        }
+        // earlier[i] has a bit set for each page that must come before page
+        // i. Rules both ways between two pages leave neither able to go
+        // first, so there are no orders.
+        let earlier: Vec<u32> = pages
+            .iter()
+            .map(|&page| {
+                (0..pages.len())
+                    .filter(|&j| self.has_rule(pages[j], page))
+                    .fold(0, |mask, j| mask | 1 << j)
+            })
+            .collect();
+        // ways[set] is the number of ways to print the pages in `set` first.
+        let mut ways = vec![0u64; 1 << pages.len()];
+        ways[0] = 1;
+        for set in 0..ways.len() {
+            if ways[set] == 0 {
+                continue;
+            }
+            for (i, &before) in earlier.iter().enumerate() {
+                if set & 1 << i == 0 && before & !set as u32 == 0 {
+                    let next = set | 1 << i;
+                    ways[next] = ways[next].saturating_add(ways[set]).min(cap);
+                }
+            }
+        }
+        Ok(ways[ways.len() - 1].min(cap))
+    }
+
+    // Lists the orders of `pages` that follow every rule, generating each
+    // one only when it's asked for.
+    fn orders<'a>(&'a self, pages: &'a [i32]) -> Orders<'a> {
+        let earlier = pages
+            .iter()
+            .map(|&page| {
+                (0..pages.len())
+                    .filter(|&j| self.has_rule(pages[j], page))
+                    .collect()
+            })
+            .collect();
+        Orders {
+            pages,
+            earlier,
+            placed: vec![false; pages.len()],
+            order: Vec::with_capacity(pages.len()),
+            next: vec![0],
+        }
+    }
+
+    // Draws the rules as a Graphviz digraph, one node per page and one edge
+    // per rule.
+    fn to_dot(&self, options: &DotOptions) -> Result<String> {
+        let mut rules: Vec<(i32, i32)> = self
+            .ordering_rules
+            .iter()
+            .flat_map(|(&before, after)| after.iter().map(move |&a| (before, a)))
+            .collect();
+        let mut pages: BTreeSet<i32> = rules.iter().flat_map(|&(a, b)| [a, b]).collect();
+        if let Some(update) = options.update {
+            let update = self
+                .updates
+                .get(update)
+                .with_context(|| format!("no update {update}"))?;
+            pages = update.pages.iter().copied().collect();
+            rules.retain(|(a, b)| pages.contains(a) && pages.contains(b));
+        }
+        rules.sort_unstable();
+
+        let broken: HashSet<(i32, i32)> = if options.highlight {
+            Analysis::new(self)
+                .violations
+                .iter()
+                .filter(|&&(update, _)| options.update.is_none_or(|u| u == update))
+                .flat_map(|(_, broken)| broken.iter().map(|violation| violation.rule))
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        if options.reduce {
+            let mut later: HashMap<i32, HashSet<i32>> = HashMap::new();
+            for &(a, b) in &rules {
+                later.entry(a).or_default().insert(b);
+            }
+            // Rules can form cycles, so reduce the graph of groups of pages
+            // that reach each other instead. Rules inside a group are kept.
+            let mut group: HashMap<i32, usize> = HashMap::new();
+            for (g, component) in strongly_connected(&later).into_iter().enumerate() {
+                group.extend(component.into_iter().map(|page| (page, g)));
+            }
+            let mut later_groups: HashMap<usize, HashSet<usize>> = HashMap::new();
+            for &(a, b) in &rules {
+                if group[&a] != group[&b] {
+                    later_groups.entry(group[&a]).or_default().insert(group[&b]);
+                }
+            }
+            let reachable = |from: usize| {
+                let mut seen = HashSet::new();
+                let mut stack = vec![from];
+                while let Some(g) = stack.pop() {
+                    for &next in later_groups.get(&g).into_iter().flatten() {
+                        if seen.insert(next) {
+                            stack.push(next);
                         }
-                        pages.swap(i, j);
-                        break;
                     }
                 }
+                seen
+            };
+            let reach: HashMap<usize, HashSet<usize>> = later_groups
+                .values()
+                .flatten()
+                .map(|&g| (g, reachable(g)))
+                .collect();
+            // A rule a|b between groups follows from the others if b's group
+            // can be reached from another group that must come after a's.
+            // Broken rules are kept so they still show up.
+            rules.retain(|&(a, b)| {
+                let (from, to) = (group[&a], group[&b]);
+                from == to
+                    || broken.contains(&(a, b))
+                    || !later_groups[&from]
+                        .iter()
+                        .any(|&g| g != to && reach[&g].contains(&to))
+            });
+        }
+
+        let mut dot = String::from("digraph rules {\n");
+        for page in &pages {
+            dot.push_str(&format!("    {page};\n"));
+        }
+        for rule in &rules {
+            let colour = if broken.contains(rule) {
+                " [color=red]"
+            } else {
+                ""
+            };
+            dot.push_str(&format!("    {} -> {}{colour};\n", rule.0, rule.1));
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+
+    // Returns true if there's a rule `before|after`.
+    fn has_rule(&self, before: i32, after: i32) -> bool {
+        self.ordering_rules
+            .get(&before)
+            .is_some_and(|later| later.contains(&after))
+    }
+
+    // Orders two pages by the rule between them, or Equal if there isn't
+    // one or there are rules both ways.
+    fn compare(&self, a: i32, b: i32) -> Ordering {
+        match (self.has_rule(a, b), self.has_rule(b, a)) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+
+    // Puts `pages` in order with Kahn's algorithm, and checks that the
+    // rules allow no other order. Once the rules are known to be acyclic, a
+    // valid order is the only one if each page has a rule putting it right
+    // before the next: any pair of neighbours without one could be swapped.
+    fn sort_by_rules(&self, pages: &[i32]) -> Result<Vec<i32>, OrderError> {
+        let order = self.sorted(pages)?;
+        if let Some(i) = order
+            .windows(2)
+            .position(|pair| self.compare(pair[0], pair[1]) != Ordering::Less)
+        {
+            let mut other = order.clone();
+            other.swap(i, i + 1);
+            return Err(OrderError::Ambiguous(order, other));
+        }
+        Ok(order)
+    }
+
+    // Orders `pages` so that every rule between two of them is followed,
+    // using Kahn's algorithm on just the rules that apply. Pages the rules
+    // don't order keep their relative order.
+    fn sorted(&self, pages: &[i32]) -> Result<Vec<i32>, Cycle> {
+        let ordering_rules = &self.ordering_rules;
+        let empty_hashset = HashSet::new();
+        // later[i] holds the indices of pages that must come after page i.
+        let later: Vec<Vec<usize>> = pages
+            .iter()
+            .map(|page| {
+                let after = ordering_rules.get(page).unwrap_or(&empty_hashset);
+                (0..pages.len())
+                    .filter(|&j| after.contains(&pages[j]))
+                    .collect()
+            })
+            .collect();
+        let mut earlier_count = vec![0; pages.len()];
+        for &j in later.iter().flatten() {
+            earlier_count[j] += 1;
+        }
+
+        let mut ready: BTreeSet<usize> = (0..pages.len())
+            .filter(|&i| earlier_count[i] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(pages.len());
+        while let Some(i) = ready.pop_first() {
+            order.push(pages[i]);
+            for &j in &later[i] {
+                earlier_count[j] -= 1;
+                if earlier_count[j] == 0 {
+                    ready.insert(j);
+                }
             }
-            let middle_page = pages[pages.len() / 2];
-            sum += middle_page;
+        }
+        if order.len() == pages.len() {
+            return Ok(order);
+        }
+
+        // Every page left over has a page before it that's also left over, so
+        // walking backwards from any of them must eventually repeat a page.
+        let mut earlier: Vec<Vec<usize>> = vec![Vec::new(); pages.len()];
+        for (i, after) in later.iter().enumerate() {
+            for &j in after {
+                earlier[j].push(i);
+            }
+        }
+        let mut walk = vec![(0..pages.len()).find(|&i| earlier_count[i] > 0).unwrap()];
+        loop {
+            let current = *walk.last().unwrap();
+            let previous = *earlier[current]
+                .iter()
+                .find(|&&i| earlier_count[i] > 0)
+                .unwrap();
+            if let Some(start) = walk.iter().position(|&i| i == previous) {
+                let mut cycle: Vec<i32> = walk[start..].iter().map(|&i| pages[i]).collect();
+                // The walk went backwards through the rules.
+                cycle.reverse();
+                return Err(Cycle { pages: cycle });
+            }
+            walk.push(previous);
         }
     }
+}
 
-    sum
+// What PrintQueue::to_dot draws.
+#[derive(Debug, Default)]
+struct DotOptions {
+    // Only draw the pages of this update, by index, and the rules between
+    // them.
+    update: Option<usize>,
+    // Colour the rules broken by the chosen update, or by any update, red.
+    highlight: bool,
+    // Leave out rules implied by a chain of other rules. Within a cycle,
+    // every rule is kept.
+    reduce: bool,
 }
 
+// The largest update count_orders will take on, as it needs a counter for
+// every subset of the pages: 8 MiB of them at this size.
+const MAX_COUNTED_PAGES: usize = 20;
+
+// The orders of an update's pages that follow every rule, found by
+// placing one page at a time and backtracking.
+struct Orders<'a> {
+    pages: &'a [i32],
+    // The indices of the pages that must come before each page.
+    earlier: Vec<Vec<usize>>,
+    placed: Vec<bool>,
+    // Indices of the pages placed so far, in order.
+    order: Vec<usize>,
+    // For each position up to the next one to fill, the lowest page index
+    // not yet tried there.
+    next: Vec<usize>,
+}
+
+impl Iterator for Orders<'_> {
+    type Item = Vec<i32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.pages.len();
+        while let Some(next) = self.next.last_mut() {
+            if self.order.len() == n {
+                // A complete order, seen the first time we get here; after
+                // that, back up.
+                if *next == 0 {
+                    *next = n + 1;
+                    return Some(self.order.iter().map(|&i| self.pages[i]).collect());
+                }
+            } else if let Some(i) = (*next..n)
+                .find(|&i| !self.placed[i] && self.earlier[i].iter().all(|&j| self.placed[j]))
+            {
+                *next = i + 1;
+                self.placed[i] = true;
+                self.order.push(i);
+                self.next.push(0);
+                continue;
+            }
+            self.next.pop();
+            if let Some(i) = self.order.pop() {
+                self.placed[i] = false;
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Update {
     pages: Vec<i32>,
 }
@@ -115,12 +655,612 @@ struct Update {
 impl FromStr for Update {
     type Err = Error;
 
+    // Parses comma-separated pages, none of them repeated.
     fn from_str(s: &str) -> Result<Self> {
-        let pages = s
+        let pages: Vec<i32> = s
             .split(',')
-            .map(|n| n.parse::<i32>().expect("number"))
+            .map(|n| n.parse().with_context(|| format!("bad page {n:?}")))
+            .collect::<Result<_>>()?;
+        let mut seen = HashSet::new();
+        if let Some(page) = pages.iter().find(|&&page| !seen.insert(page)) {
+            bail!("page {page} appears more than once");
+        }
+        Ok(Self { pages })
+    }
+}
+
+// The rules that apply to an update contradict each other: following them
+// leads from each of `pages` to the next and back to the first.
+#[derive(Debug, Clone, PartialEq)]
+struct Cycle {
+    pages: Vec<i32>,
+}
+
+impl Cycle {
+    // The rules making up the cycle, as (before, after) pairs.
+    fn rules(&self) -> Vec<(i32, i32)> {
+        let next = self.pages.iter().cycle().skip(1);
+        self.pages.iter().copied().zip(next.copied()).collect()
+    }
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rules: Vec<String> = self
+            .rules()
+            .iter()
+            .map(|(before, after)| format!("{before}|{after}"))
             .collect();
+        write!(
+            f,
+            "rules form a cycle through pages {:?}: {}",
+            self.pages,
+            rules.join(", ")
+        )
+    }
+}
 
-        Ok(Self { pages })
+impl std::error::Error for Cycle {}
+
+// Why the rules don't pin down a single order for an update's pages.
+#[derive(Debug, Clone, PartialEq)]
+enum OrderError {
+    Cycle(Cycle),
+    // Two different orders that both follow every rule.
+    Ambiguous(Vec<i32>, Vec<i32>),
+}
+
+impl From<Cycle> for OrderError {
+    fn from(cycle: Cycle) -> Self {
+        Self::Cycle(cycle)
+    }
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cycle(cycle) => cycle.fmt(f),
+            Self::Ambiguous(first, second) => write!(
+                f,
+                "rules allow more than one order, e.g. {first:?} and {second:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
+// Ordering rules that can be added and removed one at a time, keeping
+// track of every page each page must come before, directly or through
+// other rules. Adding a rule that would create a cycle is refused.
+#[derive(Debug, Default)]
+struct RuleSet {
+    // The pages that must come after each page, by a single rule.
+    later: HashMap<i32, HashSet<i32>>,
+    // The pages that must come after each page, following any number of
+    // rules.
+    reachable: HashMap<i32, HashSet<i32>>,
+}
+
+impl RuleSet {
+    // Adds the rule `before|after`, unless the rules already put `after`
+    // before `before`, in which case the rules that would form a cycle
+    // with it are returned.
+    fn add(&mut self, before: i32, after: i32) -> Result<(), Cycle> {
+        if before == after || self.must_precede(after, before) {
+            let mut pages = self.path(after, before);
+            pages.pop();
+            pages.insert(0, before);
+            return Err(Cycle { pages });
+        }
+        if !self.later.entry(before).or_default().insert(after) {
+            return Ok(());
+        }
+        // Everything that came before `before` now also comes before
+        // `after` and whatever follows it.
+        let mut gained = self.reachable.get(&after).cloned().unwrap_or_default();
+        gained.insert(after);
+        for (&page, reachable) in self.reachable.iter_mut() {
+            if page == before || reachable.contains(&before) {
+                reachable.extend(&gained);
+            }
+        }
+        self.reachable.entry(before).or_insert(gained);
+        Ok(())
+    }
+
+    // Removes the rule `before|after`, returning false if there was no
+    // such rule. Only the pages that could reach `before` need their
+    // reachable pages worked out again.
+    fn remove(&mut self, before: i32, after: i32) -> bool {
+        if !self
+            .later
+            .get_mut(&before)
+            .is_some_and(|l| l.remove(&after))
+        {
+            return false;
+        }
+        let affected: Vec<i32> = self
+            .reachable
+            .iter()
+            .filter(|&(&page, reachable)| page == before || reachable.contains(&before))
+            .map(|(&page, _)| page)
+            .collect();
+        for page in affected {
+            let mut reachable = HashSet::new();
+            let mut stack = vec![page];
+            while let Some(current) = stack.pop() {
+                for &next in self.later.get(&current).into_iter().flatten() {
+                    if reachable.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+            self.reachable.insert(page, reachable);
+        }
+        true
+    }
+
+    // Returns true if the rules put `before` ahead of `after`.
+    fn must_precede(&self, before: i32, after: i32) -> bool {
+        self.reachable
+            .get(&before)
+            .is_some_and(|reachable| reachable.contains(&after))
+    }
+
+    // Returns true if no rule between two of `pages` puts a later one
+    // first. Like the puzzle, this ignores chains of rules through pages
+    // that aren't in the update; must_precede follows those.
+    fn is_valid(&self, pages: &[i32]) -> bool {
+        pages.iter().enumerate().all(|(i, page)| {
+            self.later
+                .get(page)
+                .is_none_or(|later| !pages[..i].iter().any(|earlier| later.contains(earlier)))
+        })
+    }
+
+    // Finds a chain of rules from `from` to `to`, which must exist, as the
+    // pages along it.
+    fn path(&self, from: i32, to: i32) -> Vec<i32> {
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(page) = queue.pop_front() {
+            if page == to {
+                break;
+            }
+            for &next in self.later.get(&page).into_iter().flatten() {
+                if next != from && !previous.contains_key(&next) {
+                    previous.insert(next, page);
+                    queue.push_back(next);
+                }
+            }
+        }
+        let mut path = vec![to];
+        while *path.last().unwrap() != from {
+            path.push(previous[path.last().unwrap()]);
+        }
+        path.reverse();
+        path
+    }
+}
+
+// A rule an update breaks: `rule.0` must come before `rule.1`, but they're
+// at `positions.0` and `positions.1` in the update, the other way round.
+#[derive(Debug, Clone, PartialEq)]
+struct Violation {
+    rule: (i32, i32),
+    positions: (usize, usize),
+}
+
+// Problems with a set of ordering rules and the updates checked against
+// them.
+#[derive(Debug, Default)]
+struct Analysis {
+    // Pairs of pages with rules ordering them both ways, as (a, b) with
+    // a < b.
+    contradictions: Vec<(i32, i32)>,
+    // One cycle through each group of pages whose rules lead from any of
+    // them to all the others.
+    cycles: Vec<Vec<i32>>,
+    // Pages in some update that no rule mentions.
+    unruled_pages: Vec<i32>,
+    // Rules between two pages that never appear in the same update.
+    unused_rules: Vec<(i32, i32)>,
+    // The rules broken by each invalid update, by update index.
+    violations: Vec<(usize, Vec<Violation>)>,
+}
+
+impl Analysis {
+    fn new(queue: &PrintQueue) -> Self {
+        let ordering_rules = &queue.ordering_rules;
+        let updates = &queue.updates;
+        let mut rules: Vec<(i32, i32)> = ordering_rules
+            .iter()
+            .flat_map(|(&before, after)| after.iter().map(move |&a| (before, a)))
+            .collect();
+        rules.sort_unstable();
+        let follows = |before: i32, after: i32| {
+            ordering_rules
+                .get(&before)
+                .is_some_and(|later| later.contains(&after))
+        };
+
+        let contradictions = rules
+            .iter()
+            .copied()
+            .filter(|&(a, b)| a < b && follows(b, a))
+            .collect();
+
+        let ruled_pages: HashSet<i32> = rules.iter().flat_map(|&(a, b)| [a, b]).collect();
+        let unruled_pages: BTreeSet<i32> = updates
+            .iter()
+            .flat_map(|u| u.pages.iter().copied())
+            .filter(|page| !ruled_pages.contains(page))
+            .collect();
+
+        let update_pages: Vec<HashSet<i32>> = updates
+            .iter()
+            .map(|u| u.pages.iter().copied().collect())
+            .collect();
+        let unused_rules = rules
+            .iter()
+            .copied()
+            .filter(|(a, b)| !update_pages.iter().any(|p| p.contains(a) && p.contains(b)))
+            .collect();
+
+        let violations = updates
+            .iter()
+            .enumerate()
+            .filter_map(|(u, update)| {
+                let pages = &update.pages;
+                let broken: Vec<Violation> = (0..pages.len())
+                    .flat_map(|i| (i + 1..pages.len()).map(move |j| (i, j)))
+                    .filter(|&(i, j)| follows(pages[j], pages[i]))
+                    .map(|(i, j)| Violation {
+                        rule: (pages[j], pages[i]),
+                        positions: (j, i),
+                    })
+                    .collect();
+                (!broken.is_empty()).then_some((u, broken))
+            })
+            .collect();
+
+        Self {
+            contradictions,
+            cycles: cycles(ordering_rules),
+            unruled_pages: unruled_pages.into_iter().collect(),
+            unused_rules,
+            violations,
+        }
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = |&(a, b): &(i32, i32)| format!("{a}|{b}");
+        let contradictions: Vec<String> = self
+            .contradictions
+            .iter()
+            .map(|&(a, b)| format!("{a}|{b} and {b}|{a}"))
+            .collect();
+        writeln!(f, "contradictory rules: {}", contradictions.len())?;
+        for contradiction in contradictions {
+            writeln!(f, "  {contradiction}")?;
+        }
+        writeln!(f, "cycles: {}", self.cycles.len())?;
+        for cycle in &self.cycles {
+            let pages: Vec<String> = cycle.iter().map(i32::to_string).collect();
+            writeln!(f, "  {} -> {}", pages.join(" -> "), cycle[0])?;
+        }
+        writeln!(
+            f,
+            "pages in updates with no rules: {:?}",
+            self.unruled_pages
+        )?;
+        let unused: Vec<String> = self.unused_rules.iter().map(rule).collect();
+        writeln!(f, "rules no update uses: {}", unused.len())?;
+        if !unused.is_empty() {
+            writeln!(f, "  {}", unused.join(", "))?;
+        }
+        writeln!(f, "invalid updates: {}", self.violations.len())?;
+        for (update, broken) in &self.violations {
+            writeln!(f, "  update {update}:")?;
+            for violation in broken {
+                let (before, after) = violation.positions;
+                writeln!(
+                    f,
+                    "    {} broken: page {} at position {before} comes after page {} at position {after}",
+                    rule(&violation.rule),
+                    violation.rule.0,
+                    violation.rule.1,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Returns one cycle through each strongly connected group of pages in the
+// rule graph, and each page with a rule to itself.
+fn cycles(ordering_rules: &HashMap<i32, HashSet<i32>>) -> Vec<Vec<i32>> {
+    // Inside a group, every page has a rule to another page in the group,
+    // so following them from any page must come back around. A page on its
+    // own only forms a cycle with a rule putting it before itself.
+    strongly_connected(ordering_rules)
+        .into_iter()
+        .filter(|component| {
+            component.len() > 1
+                || ordering_rules
+                    .get(&component[0])
+                    .is_some_and(|later| later.contains(&component[0]))
+        })
+        .map(|component| {
+            let members: HashSet<i32> = component.iter().copied().collect();
+            let mut walk = vec![*component.iter().min().unwrap()];
+            loop {
+                let current = *walk.last().unwrap();
+                let next = *ordering_rules[&current]
+                    .iter()
+                    .filter(|page| members.contains(page))
+                    .min()
+                    .unwrap();
+                if let Some(start) = walk.iter().position(|&p| p == next) {
+                    return walk.split_off(start);
+                }
+                walk.push(next);
+            }
+        })
+        .collect()
+}
+
+// Splits the pages in the rule graph into groups whose rules lead from any
+// page in the group to all the others, with Tarjan's algorithm. A page on
+// no cycle is a group of its own.
+fn strongly_connected(ordering_rules: &HashMap<i32, HashSet<i32>>) -> Vec<Vec<i32>> {
+    struct Tarjan<'a> {
+        rules: &'a HashMap<i32, HashSet<i32>>,
+        index: HashMap<i32, usize>,
+        low_link: HashMap<i32, usize>,
+        stack: Vec<i32>,
+        on_stack: HashSet<i32>,
+        components: Vec<Vec<i32>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, page: i32) {
+            let index = self.index.len();
+            self.index.insert(page, index);
+            self.low_link.insert(page, index);
+            self.stack.push(page);
+            self.on_stack.insert(page);
+            let mut later: Vec<i32> = self
+                .rules
+                .get(&page)
+                .map_or(Vec::new(), |l| l.iter().copied().collect());
+            later.sort_unstable();
+            for next in later {
+                if !self.index.contains_key(&next) {
+                    self.visit(next);
+                    let low = self.low_link[&page].min(self.low_link[&next]);
+                    self.low_link.insert(page, low);
+                } else if self.on_stack.contains(&next) {
+                    let low = self.low_link[&page].min(self.index[&next]);
+                    self.low_link.insert(page, low);
+                }
+            }
+            if self.low_link[&page] == index {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(&member);
+                    component.push(member);
+                    if member == page {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut pages: Vec<i32> = ordering_rules
+        .iter()
+        .flat_map(|(&a, later)| later.iter().copied().chain([a]))
+        .collect::<HashSet<i32>>()
+        .into_iter()
+        .collect();
+    pages.sort_unstable();
+    let mut tarjan = Tarjan {
+        rules: ordering_rules,
+        index: HashMap::new(),
+        low_link: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+    for page in pages {
+        if !tarjan.index.contains_key(&page) {
+            tarjan.visit(page);
+        }
+    }
+    tarjan.components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &[(i32, i32)]) -> HashMap<i32, HashSet<i32>> {
+        let mut later: HashMap<i32, HashSet<i32>> = HashMap::new();
+        for &(before, after) in rules {
+            later.entry(before).or_default().insert(after);
+        }
+        later
+    }
+
+    #[test]
+    fn cycles_include_self_rules() {
+        assert_eq!(cycles(&rules(&[(3, 3), (1, 5)])), [vec![3]]);
+        assert_eq!(cycles(&rules(&[(1, 2), (2, 1), (2, 3)])), [vec![1, 2]]);
+        assert!(cycles(&rules(&[(1, 2), (2, 3), (1, 3)])).is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_self_rules() {
+        let err = PrintQueue::parse("1|5\n3|3\n\n1,3,5", MiddlePolicy::default()).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: page 3 can't come before itself");
+    }
+
+    fn queue(input: &str) -> PrintQueue {
+        PrintQueue::parse(input, MiddlePolicy::default()).unwrap()
+    }
+
+    // Returns true if `order` breaks none of `queue`'s rules.
+    fn follows_rules(queue: &PrintQueue, order: &[i32]) -> bool {
+        (0..order.len()).all(|i| (i + 1..order.len()).all(|j| !queue.has_rule(order[j], order[i])))
+    }
+
+    #[test]
+    fn sort_by_rules_finds_the_only_order() {
+        let queue = queue("1|2\n2|3\n1|3\n\n3,1,2");
+        assert_eq!(queue.sort_by_rules(&[3, 1, 2]), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn sort_by_rules_reports_two_orders_when_ambiguous() {
+        let queue = queue("1|2\n1|3\n\n3,2,1");
+        let Err(OrderError::Ambiguous(first, second)) = queue.sort_by_rules(&[3, 2, 1]) else {
+            panic!("expected two orders");
+        };
+        assert_ne!(first, second);
+        for order in [first, second] {
+            let mut pages = order.clone();
+            pages.sort_unstable();
+            assert_eq!(pages, [1, 2, 3]);
+            assert!(follows_rules(&queue, &order), "{order:?}");
+        }
+    }
+
+    #[test]
+    fn sort_by_rules_reports_cycles() {
+        let queue = queue("1|2\n2|3\n3|1\n3|4\n\n4,3,2,1,5");
+        let Err(OrderError::Cycle(cycle)) = queue.sort_by_rules(&[4, 3, 2, 1, 5]) else {
+            panic!("expected a cycle");
+        };
+        let mut pages = cycle.pages.clone();
+        pages.sort_unstable();
+        assert_eq!(pages, [1, 2, 3]);
+        for (before, after) in cycle.rules() {
+            assert!(queue.has_rule(before, after), "{before}|{after}");
+        }
+    }
+
+    #[test]
+    fn reordered_middle_pages_keeps_a_pinned_middle() {
+        // Update 0 has four orders, all with 3 in the middle. Update 1's
+        // orders put 6, 7 or 8 in the middle.
+        let queue = queue("1|3\n2|3\n3|4\n3|5\n6|7\n\n4,3,1,2,5\n7,6,8");
+        let (sum, unsorted) = queue.reordered_middle_pages();
+        assert_eq!(sum, 3);
+        assert_eq!(unsorted.len(), 1);
+        assert_eq!(unsorted[0].0, 1);
+        assert!(matches!(unsorted[0].1, OrderError::Ambiguous(..)));
+    }
+
+    // Random acyclic rules between pages 1 to `pages`, from a simple linear
+    // congruential generator so failures are reproducible, with the pages
+    // as one shuffled update.
+    fn random_queue(seed: u64, pages: i32) -> PrintQueue {
+        let mut state = seed;
+        let mut next = |n: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % n
+        };
+        let mut rules = Vec::new();
+        for a in 1..=pages {
+            for b in a + 1..=pages {
+                if next(3) == 0 {
+                    rules.push(format!("{a}|{b}"));
+                }
+            }
+        }
+        let mut update: Vec<i32> = (1..=pages).collect();
+        for i in (1..update.len()).rev() {
+            update.swap(i, next(i as u64 + 1) as usize);
+        }
+        let update: Vec<String> = update.iter().map(i32::to_string).collect();
+        queue(&format!("{}\n\n{}", rules.join("\n"), update.join(",")))
+    }
+
+    #[test]
+    fn is_pinned_matches_every_order() {
+        for seed in 0..300 {
+            let queue = random_queue(seed, 7);
+            let pages = &queue.updates[0].pages;
+            let order = queue.sorted(pages).unwrap();
+            let orders: Vec<Vec<i32>> = queue.orders(pages).collect();
+            for i in 0..order.len() {
+                let pinned = orders.iter().all(|other| other[i] == order[i]);
+                assert_eq!(queue.is_pinned(&order, i), pinned, "{queue:?} at {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn rules_both_ways_allow_no_orders() {
+        let queue = queue("1|2\n2|1\n\n1,2,3");
+        assert_eq!(queue.count_orders(&[1, 2, 3], 1000).unwrap(), 0);
+        assert_eq!(queue.orders(&[1, 2, 3]).next(), None);
+    }
+
+    #[test]
+    fn count_orders_matches_orders() {
+        for seed in 0..300 {
+            let queue = random_queue(seed, 7);
+            let pages = &queue.updates[0].pages;
+            let orders: Vec<Vec<i32>> = queue.orders(pages).collect();
+            assert_eq!(
+                queue.count_orders(pages, u64::MAX).unwrap(),
+                orders.len() as u64,
+                "{queue:?}"
+            );
+            assert_eq!(
+                queue.count_orders(pages, 5).unwrap(),
+                orders.len().min(5) as u64
+            );
+            for order in &orders {
+                assert!(follows_rules(&queue, order), "{order:?}");
+            }
+            let distinct: HashSet<&Vec<i32>> = orders.iter().collect();
+            assert_eq!(distinct.len(), orders.len(), "{queue:?}");
+        }
+    }
+
+    #[test]
+    fn count_orders_refuses_large_updates() {
+        let pages: Vec<i32> = (1..=MAX_COUNTED_PAGES as i32 + 1).collect();
+        assert!(queue("1|2\n").count_orders(&pages, 1).is_err());
+    }
+
+    #[test]
+    fn to_dot_reduces_around_cycles() {
+        // 1, 2 and 3 form a cycle, which 3|5 and 1|5 both leave for 5,
+        // already reached through 4. 6|7 and 7|8 make 6|8 redundant.
+        let queue = queue("1|2\n2|3\n3|1\n3|4\n4|5\n3|5\n1|5\n6|7\n7|8\n6|8\n");
+        let options = DotOptions {
+            reduce: true,
+            ..DotOptions::default()
+        };
+        let dot = queue.to_dot(&options).unwrap();
+        let edges: Vec<&str> = dot
+            .lines()
+            .filter(|line| line.contains("->"))
+            .map(str::trim)
+            .collect();
+        assert_eq!(
+            edges,
+            ["1 -> 2;", "2 -> 3;", "3 -> 1;", "3 -> 4;", "4 -> 5;", "6 -> 7;", "7 -> 8;"]
+        );
     }
 }
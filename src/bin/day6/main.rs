@@ -1,220 +1,81 @@
-use std::fs;
+use std::{env, fs, hint::black_box, process, time::Instant};
 
-fn main() {
-    println!("part1: {}", part1());
-    println!("part2: {}", part2());
-}
-
-fn part1() -> i32 {
-    let input = fs::read_to_string("example.txt").unwrap();
-    let total_cols = input.lines().next().unwrap().len();
-    let total_rows = input.lines().count();
-
-    // Build the maps and initialize guard.
-    let mut obstructions: PositionMap<bool> = PositionMap::new(total_rows, total_cols);
-    let mut visited_positions: PositionMap<bool> = PositionMap::new(total_rows, total_cols);
-    let mut guard = {
-        let mut guard_row = 0;
-        let mut guard_col = 0;
-        for (col, line) in input.lines().enumerate() {
-            for (row, char) in line.char_indices() {
-                if char == '#' {
-                    obstructions.set(row, col, true);
-                } else if char == '^' {
-                    guard_row = row;
-                    guard_col = col;
-                    visited_positions.set(row, col, true);
-                }
-            }
-        }
-        Guard::new(guard_row, guard_col)
-    };
+use aoc2024::day6::{self, DirectionGrid, JumpTable, Lab};
 
-    // Run the simulation.
-    loop {
-        let Some((next_row, next_col)) = guard.next() else {
-            // Leaving area up or to left.
-            break;
-        };
-        if next_row == total_rows || next_col == total_cols {
-            // Leaving area down or to right.
-            break;
-        }
-        if *obstructions.get(next_row, next_col) {
-            guard.turn();
-        } else {
-            visited_positions.set(next_row, next_col, true);
-            guard.move_forward();
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        // `cargo run --bin day6 -- brute [file]` runs part2 the slow way, with
+        // an obstruction in every free cell, to check the answer against.
+        Some("brute") => {
+            let input = fs::read_to_string(args.get(2).map_or("input.txt", String::as_str));
+            let lab: Lab = input.unwrap().parse().unwrap();
+            println!("part2: {}", lab.brute_force_loop_obstructions());
         }
-    }
-
-    visited_positions.count() as i32
-}
-
-fn part2() -> i32 {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let total_cols = input.lines().next().unwrap().len();
-    let total_rows = input.lines().count();
-
-    // Build the maps and initialize guard.
-    let mut initial_obstructions: PositionMap<bool> = PositionMap::new(total_rows, total_cols);
-    let mut initial_visited_positions: PositionMap<Option<Direction>> =
-        PositionMap::new(total_rows, total_cols);
-    let initial_guard = {
-        let mut guard_row = 0;
-        let mut guard_col = 0;
-        for (col, line) in input.lines().enumerate() {
-            for (row, char) in line.char_indices() {
-                if char == '#' {
-                    initial_obstructions.set(row, col, true);
-                } else if char == '^' {
-                    guard_row = row;
-                    guard_col = col;
-                    initial_visited_positions.set(row, col, Some(Direction::Up));
-                }
+        Some("bench") => bench(&args[2..]),
+        Some("viz") => {
+            if let Err(err) = day6::viz(&args[2..], "input.txt") {
+                eprintln!("error: {err:#}");
+                process::exit(1);
             }
         }
-        Guard::new(guard_row, guard_col)
-    };
-
-    let mut permutations = 0;
-
-    for col in 0..total_cols {
-        for row in 0..total_rows {
-            let on_guards_current_spot = row == initial_guard.row && col == initial_guard.col;
-            if on_guards_current_spot || *initial_obstructions.get(row, col) {
-                continue;
-            }
-
-            let mut guard = initial_guard.clone();
-            let mut visited_positions = initial_visited_positions.clone();
-            let mut obstructions = initial_obstructions.clone();
-            obstructions.set(row, col, true);
-
-            // Run the simulation.
-            loop {
-                let Some((next_row, next_col)) = guard.next() else {
-                    // Leaving area up or to left.
-                    break;
-                };
-                if next_row == total_rows || next_col == total_cols {
-                    // Leaving area down or to right.
-                    break;
-                }
-                if let Some(previous_direction) = visited_positions.get(next_row, next_col) {
-                    if *previous_direction == guard.direction {
-                        // We've created a loop for the guard;
-                        permutations += 1;
-                        break;
-                    }
-                }
-                if *obstructions.get(next_row, next_col) {
-                    guard.turn();
-                } else {
-                    visited_positions.set(next_row, next_col, Some(guard.direction));
-                    guard.move_forward();
-                }
-            }
+        _ => {
+            println!("part1: {}", part1());
+            println!("part2: {}", part2());
         }
     }
-
-    permutations
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Direction {
-    Up,
-    Right,
-    Down,
-    Left,
-}
-
-#[derive(Debug, Clone)]
-struct Guard {
-    pub row: usize,
-    pub col: usize,
-    pub direction: Direction,
+fn part1() -> i32 {
+    let input = fs::read_to_string("example.txt").unwrap();
+    let lab: Lab = input.parse().unwrap();
+    lab.visited().count() as i32
 }
 
-impl Guard {
-    fn new(row: usize, col: usize) -> Self {
-        Self {
-            row,
-            col,
-            direction: Direction::Up,
-        }
-    }
-
-    fn next(&self) -> Option<(usize, usize)> {
-        match self.direction {
-            Direction::Up => {
-                if self.col == 0 {
-                    None
-                } else {
-                    Some((self.row, self.col - 1))
-                }
-            }
-            Direction::Right => Some((self.row + 1, self.col)),
-            Direction::Down => Some((self.row, self.col + 1)),
-            Direction::Left => {
-                if self.row == 0 {
-                    None
-                } else {
-                    Some((self.row - 1, self.col))
-                }
-            }
-        }
-    }
-
-    fn move_forward(&mut self) {
-        let (row, col) = self.next().expect("move should be valid");
-        self.row = row;
-        self.col = col;
-    }
-
-    fn turn(&mut self) {
-        self.direction = match self.direction {
-            Direction::Up => Direction::Right,
-            Direction::Right => Direction::Down,
-            Direction::Down => Direction::Left,
-            Direction::Left => Direction::Up,
-        }
-    }
+fn part2() -> i32 {
+    let input = fs::read_to_string("input.txt").unwrap();
+    let lab: Lab = input.parse().unwrap();
+    lab.loop_obstructions().len() as i32
 }
 
-#[derive(Debug, Clone)]
-#[allow(unused)]
-struct PositionMap<T: Clone + Default + PartialEq> {
-    positions: Vec<T>,
-    rows: usize,
-    cols: usize,
-}
+// `cargo run --release --bin day6 -- bench [iterations]` times part2
+// moving the guard a cell at a time against jumping between turns.
+fn bench(args: &[String]) {
+    let iterations: u32 = args.first().map_or(3, |n| n.parse().unwrap());
+    let input = fs::read_to_string("input.txt").unwrap();
+    let lab: Lab = input.parse().unwrap();
 
-impl<T> PositionMap<T>
-where
-    T: Clone + Default + PartialEq,
-{
-    fn new(rows: usize, cols: usize) -> Self {
-        Self {
-            positions: vec![T::default(); rows * cols],
-            rows,
-            cols,
+    let time = |name: &str, f: &dyn Fn() -> usize| {
+        let start = Instant::now();
+        let mut result = 0;
+        for _ in 0..iterations {
+            result = black_box(f());
         }
-    }
-
-    // Set a position in the map as visited/occupied.
-    fn set(&mut self, row: usize, col: usize, val: T) {
-        self.positions[row * self.cols + col] = val;
-    }
-
-    // Return a reference to the value at the requested position.
-    fn get(&self, row: usize, col: usize) -> &T {
-        &self.positions[row * self.cols + col]
-    }
+        println!(
+            "{name:<24} {result:>6} {:>10.2?}/iter",
+            start.elapsed() / iterations
+        );
+        result
+    };
 
-    // Count all visited/occupied positions in the map.
-    fn count(&self) -> usize {
-        let default = T::default();
-        self.positions.iter().filter(|&pos| *pos != default).count()
-    }
+    let cells = time("part2 cell by cell", &|| {
+        let mut seen = DirectionGrid::new(lab.width, lab.height);
+        lab.candidates()
+            .into_iter()
+            .filter(|(guard, obstruction)| lab.loops(&mut seen, guard.clone(), *obstruction))
+            .count()
+    });
+    let jumps = time("part2 jump table", &|| {
+        let mut table = JumpTable::new(&lab);
+        let mut seen = DirectionGrid::new(lab.width, lab.height);
+        lab.candidates()
+            .into_iter()
+            .filter(|(guard, obstruction)| {
+                lab.loops_with_jumps(&mut table, &mut seen, guard.clone(), *obstruction)
+            })
+            .count()
+    });
+    assert_eq!(cells, jumps);
+    let parallel = time("part2 jump table, rayon", &|| lab.loop_obstructions().len());
+    assert_eq!(cells, parallel);
 }
@@ -67,7 +67,7 @@ impl Calibration {
                 if value > self.target_value {
                     break;
                 }
-                n = n >> 1;
+                n >>= 1;
             }
             if value == self.target_value {
                 total += value;
@@ -40,7 +40,7 @@ fn part1() -> usize {
                     // Check that p1's antinode is within map boundaries
                     (p.row < max_row && p.col < max_col)
                         // and is not on top of an antenna of the same frequency
-                        && antennas.get(&frequency).unwrap().binary_search(p).is_err()
+                        && antennas.get(frequency).unwrap().binary_search(p).is_err()
                 }) {
                     antinodes.insert(antinode);
                 }
@@ -48,7 +48,7 @@ fn part1() -> usize {
                     // Check that p2's antinode is within map boundaries
                     (p.row < max_row && p.col < max_col)
                         // and is not on top of an antenna of the same frequency
-                        && antennas.get(&frequency).unwrap().binary_search(p).is_err()
+                        && antennas.get(frequency).unwrap().binary_search(p).is_err()
                 }) {
                     antinodes.insert(antinode);
                 }
@@ -80,7 +80,7 @@ fn part2() -> usize {
     }
     // Build antinode location map.
     let mut antinodes: HashSet<Point> = HashSet::new();
-    for (_frequency, locations) in antennas.iter() {
+    for locations in antennas.values() {
         // Use 2 cursors to get all combinations of 2 points for this frequency.
         for i in 0..locations.len() - 1 {
             for j in i + 1..locations.len() {
@@ -0,0 +1,787 @@
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal, Write},
+    iter,
+    path::Path,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Error, Result};
+use rayon::prelude::*;
+
+// `viz [file] [--part2] [--loop n] [--fps n] [--every n] [--scale n]
+// [--ppm dir | --gif dir]` replays the guard's patrol of the map in `file`
+// (`default_file` if not given), as `cargo run --bin day6 -- viz` or
+// `cargo run -- viz 6`. It plays in the terminal at `--fps` frames a
+// second (30 by default) unless `--ppm` or `--gif` ask for numbered PPM
+// frames or an animated GIF in a directory, at `--scale` pixels a cell (4
+// by default). `--every` draws only every nth step. With `--part2` the map
+// gets the `--loop`th (0th by default) obstruction that traps the guard,
+// and the loop shows once the guard is caught in it.
+pub fn viz(args: &[String], default_file: &str) -> Result<()> {
+    let mut file = default_file;
+    let mut part2 = false;
+    let mut options = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--part2" {
+            part2 = true;
+        } else if let Some(name) = arg.strip_prefix("--") {
+            let value = args
+                .next()
+                .with_context(|| format!("--{name} needs a value"))?;
+            options.push((name, value.as_str()));
+        } else {
+            file = arg;
+        }
+    }
+    let option = |name: &str| {
+        options
+            .iter()
+            .find(|&&(option, _)| option == name)
+            .map(|&(_, value)| value)
+    };
+    let number = |name: &str, default: usize| -> Result<usize> {
+        option(name).map_or(Ok(default), |value| {
+            value
+                .parse()
+                .with_context(|| format!("--{name} needs a number, got {value:?}"))
+        })
+    };
+    let fps = number("fps", 30)?.max(1);
+    let every = number("every", 1)?.max(1);
+    let scale = number("scale", 4)?.max(1);
+
+    let input = fs::read_to_string(file).with_context(|| format!("reading {file}"))?;
+    let lab: Lab = input.parse()?;
+    let obstruction = if part2 {
+        let n = number("loop", 0)?;
+        let obstructions = lab.loop_obstructions();
+        let Some(&obstruction) = obstructions.get(n) else {
+            bail!("only {} obstructions make a loop", obstructions.len());
+        };
+        Some(obstruction)
+    } else {
+        None
+    };
+
+    let mut draw: DrawFrame = if let Some(dir) = option("ppm") {
+        fs::create_dir_all(dir)?;
+        let mut frame = 0;
+        Box::new(move |patrol| {
+            let path = Path::new(dir).join(format!("frame_{frame:05}.ppm"));
+            fs::write(&path, patrol.ppm(scale)).with_context(|| format!("writing {path:?}"))?;
+            frame += 1;
+            Ok(())
+        })
+    } else if let Some(dir) = option("gif") {
+        fs::create_dir_all(dir)?;
+        let path = Path::new(dir).join("patrol.gif");
+        let file = File::create(&path).with_context(|| format!("creating {path:?}"))?;
+        let width = u16::try_from(lab.width * scale).context("map too wide for a GIF")?;
+        let height = u16::try_from(lab.height * scale).context("map too tall for a GIF")?;
+        let mut encoder =
+            gif::Encoder::new(BufWriter::new(file), width, height, &PALETTE.concat())?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        // GIF delays are in hundredths of a second.
+        let delay = (100 / fps).max(1) as u16;
+        Box::new(move |patrol| {
+            let frame = gif::Frame {
+                width,
+                height,
+                delay,
+                buffer: Cow::Owned(patrol.pixels(scale)),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame)?;
+            Ok(())
+        })
+    } else {
+        let terminal = io::stdout().is_terminal();
+        let pause = Duration::from_secs(1) / fps as u32;
+        Box::new(move |patrol| {
+            let mut stdout = io::stdout().lock();
+            if terminal {
+                // Clear the screen and draw from the top left.
+                write!(stdout, "\x1b[2J\x1b[H")?;
+            }
+            writeln!(stdout, "{}", patrol.render(terminal))?;
+            stdout.flush()?;
+            thread::sleep(pause);
+            Ok(())
+        })
+    };
+
+    let mut patrol = Patrol::new(&lab, obstruction);
+    draw(&patrol)?;
+    while patrol.step() {
+        if patrol.steps.is_multiple_of(every) || patrol.finished {
+            draw(&patrol)?;
+        }
+    }
+    drop(draw);
+    match &patrol.loop_cells {
+        Some(loop_cells) => println!(
+            "guard caught in a loop through {} cells after {} steps",
+            loop_cells.count(),
+            patrol.steps
+        ),
+        None => println!(
+            "guard left after {} steps, visiting {} cells",
+            patrol.steps,
+            patrol.trail.count()
+        ),
+    }
+    Ok(())
+}
+
+// Shows or saves a frame of the patrol.
+type DrawFrame<'a> = Box<dyn FnMut(&Patrol) -> Result<()> + 'a>;
+
+// What `viz` draws in a cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tile {
+    Floor,
+    Obstruction,
+    Trail,
+    Loop,
+    Candidate,
+    Guard(Direction),
+}
+
+// The colour of each Tile in images, in the order they're declared.
+const PALETTE: [[u8; 3]; 6] = [
+    [24, 24, 32],
+    [170, 170, 170],
+    [220, 170, 40],
+    [200, 60, 220],
+    [230, 40, 40],
+    [60, 220, 60],
+];
+
+impl Tile {
+    fn palette_index(self) -> u8 {
+        match self {
+            Tile::Floor => 0,
+            Tile::Obstruction => 1,
+            Tile::Trail => 2,
+            Tile::Loop => 3,
+            Tile::Candidate => 4,
+            Tile::Guard(_) => 5,
+        }
+    }
+}
+
+// The guard's patrol replayed a step at a time for `viz`, remembering
+// where it has been.
+struct Patrol<'a> {
+    lab: &'a Lab,
+    guard: Guard,
+    obstruction: Option<(usize, usize)>,
+    // Every step taken, including turns.
+    steps: usize,
+    trail: PositionMap<bool>,
+    seen: DirectionGrid,
+    states: Vec<Guard>,
+    // Set once the guard has walked out of the lab.
+    left: bool,
+    // The cells of the loop, once the guard is found to be in one.
+    loop_cells: Option<PositionMap<bool>>,
+    finished: bool,
+}
+
+impl<'a> Patrol<'a> {
+    fn new(lab: &'a Lab, obstruction: Option<(usize, usize)>) -> Self {
+        let guard = lab.guard.clone();
+        let mut trail = PositionMap::new(lab.width, lab.height);
+        trail.set(guard.row, guard.col, true);
+        let mut seen = DirectionGrid::new(lab.width, lab.height);
+        seen.insert(guard.row, guard.col, guard.direction);
+        Self {
+            lab,
+            guard: guard.clone(),
+            obstruction,
+            steps: 0,
+            trail,
+            seen,
+            states: vec![guard],
+            left: false,
+            loop_cells: None,
+            finished: false,
+        }
+    }
+
+    // Moves the guard a step, or turns it, returning false once the patrol
+    // is over.
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.steps += 1;
+        let Some((next_row, next_col)) = self.lab.ahead(&self.guard) else {
+            self.left = true;
+            self.finished = true;
+            return true;
+        };
+        if Some((next_row, next_col)) == self.obstruction
+            || *self.lab.obstructions.get(next_row, next_col)
+        {
+            self.guard.turn();
+        } else {
+            self.guard.move_forward();
+            self.trail.set(next_row, next_col, true);
+        }
+        let guard = &self.guard;
+        if !self.seen.insert(guard.row, guard.col, guard.direction) {
+            // Everything since the guard was last here like this is the loop.
+            let start = self
+                .states
+                .iter()
+                .position(|s| {
+                    (s.row, s.col, s.direction) == (guard.row, guard.col, guard.direction)
+                })
+                .unwrap();
+            let mut loop_cells = PositionMap::new(self.lab.width, self.lab.height);
+            for state in &self.states[start..] {
+                loop_cells.set(state.row, state.col, true);
+            }
+            self.loop_cells = Some(loop_cells);
+            self.finished = true;
+        }
+        self.states.push(guard.clone());
+        true
+    }
+
+    fn tile(&self, row: usize, col: usize) -> Tile {
+        if !self.left && (row, col) == (self.guard.row, self.guard.col) {
+            Tile::Guard(self.guard.direction)
+        } else if Some((row, col)) == self.obstruction {
+            Tile::Candidate
+        } else if *self.lab.obstructions.get(row, col) {
+            Tile::Obstruction
+        } else if self.loop_cells.as_ref().is_some_and(|l| *l.get(row, col)) {
+            Tile::Loop
+        } else if *self.trail.get(row, col) {
+            Tile::Trail
+        } else {
+            Tile::Floor
+        }
+    }
+
+    // Draws the lab as text, like the puzzle's pictures, with `colour`
+    // adding ANSI colours.
+    fn render(&self, colour: bool) -> String {
+        let mut out = String::new();
+        for col in 0..self.lab.height {
+            for row in 0..self.lab.width {
+                let tile = self.tile(row, col);
+                let glyph = match tile {
+                    Tile::Floor => '.',
+                    Tile::Obstruction => '#',
+                    Tile::Trail => 'X',
+                    Tile::Loop => '+',
+                    Tile::Candidate => 'O',
+                    Tile::Guard(Direction::Up) => '^',
+                    Tile::Guard(Direction::Right) => '>',
+                    Tile::Guard(Direction::Down) => 'v',
+                    Tile::Guard(Direction::Left) => '<',
+                };
+                let code = match tile {
+                    Tile::Floor => "2",
+                    Tile::Obstruction => "0",
+                    Tile::Trail => "33",
+                    Tile::Loop => "1;35",
+                    Tile::Candidate => "1;31",
+                    Tile::Guard(_) => "1;32",
+                };
+                if colour {
+                    out.push_str(&format!("\x1b[{code}m{glyph}\x1b[0m"));
+                } else {
+                    out.push(glyph);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // Returns the palette index of every pixel, a `scale` by `scale`
+    // square per cell, a line of the map at a time.
+    fn pixels(&self, scale: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.lab.width * self.lab.height * scale * scale);
+        for col in 0..self.lab.height {
+            let line: Vec<u8> = (0..self.lab.width)
+                .flat_map(|row| iter::repeat_n(self.tile(row, col).palette_index(), scale))
+                .collect();
+            for _ in 0..scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+        pixels
+    }
+
+    // Returns the frame as a binary PPM image.
+    fn ppm(&self, scale: usize) -> Vec<u8> {
+        let (width, height) = (self.lab.width * scale, self.lab.height * scale);
+        let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+        for index in self.pixels(scale) {
+            ppm.extend_from_slice(&PALETTE[index as usize]);
+        }
+        ppm
+    }
+}
+
+// The lab floor: where the obstructions are and where the guard starts.
+//
+// Like the guard, the map takes `row` as the position along a line and
+// `col` as the line number, so it's `width` rows by `height` cols.
+#[derive(Debug, Clone)]
+pub struct Lab {
+    pub obstructions: PositionMap<bool>,
+    pub width: usize,
+    pub height: usize,
+    pub guard: Guard,
+}
+
+impl FromStr for Lab {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let width = s.lines().next().map_or(0, str::len);
+        let height = s.lines().count();
+        let mut obstructions = PositionMap::new(width, height);
+        let mut guard = None;
+        for (col, line) in s.lines().enumerate() {
+            if line.len() != width {
+                bail!("line {} is {} long, expected {width}", col + 1, line.len());
+            }
+            for (row, char) in line.char_indices() {
+                match char {
+                    '#' => obstructions.set(row, col, true),
+                    '^' => guard = Some(Guard::new(row, col)),
+                    '.' => {}
+                    _ => bail!("line {}: unexpected {char:?}", col + 1),
+                }
+            }
+        }
+        let Some(guard) = guard else {
+            bail!("no guard (^) on the map");
+        };
+        Ok(Self {
+            obstructions,
+            width,
+            height,
+            guard,
+        })
+    }
+}
+
+impl Lab {
+    // Returns the cell in front of the guard, or None if that's outside
+    // the lab.
+    fn ahead(&self, guard: &Guard) -> Option<(usize, usize)> {
+        guard
+            .next()
+            .filter(|&(row, col)| row < self.width && col < self.height)
+    }
+
+    // Returns the cells the guard visits before leaving the lab.
+    pub fn visited(&self) -> PositionMap<bool> {
+        let mut guard = self.guard.clone();
+        let mut visited_positions = PositionMap::new(self.width, self.height);
+        visited_positions.set(guard.row, guard.col, true);
+        while let Some((next_row, next_col)) = self.ahead(&guard) {
+            if *self.obstructions.get(next_row, next_col) {
+                guard.turn();
+            } else {
+                visited_positions.set(next_row, next_col, true);
+                guard.move_forward();
+            }
+        }
+        visited_positions
+    }
+
+    // Returns every cell on the guard's path where an obstruction could go,
+    // along with the guard as it was just before first reaching that cell.
+    // An obstruction anywhere else is never run into, and one on the path
+    // changes nothing before the guard first gets there.
+    pub fn candidates(&self) -> Vec<(Guard, (usize, usize))> {
+        let mut guard = self.guard.clone();
+        let mut visited_positions = PositionMap::new(self.width, self.height);
+        visited_positions.set(guard.row, guard.col, true);
+        let mut candidates = Vec::new();
+        while let Some((next_row, next_col)) = self.ahead(&guard) {
+            if *self.obstructions.get(next_row, next_col) {
+                guard.turn();
+                continue;
+            }
+            if !*visited_positions.get(next_row, next_col) {
+                visited_positions.set(next_row, next_col, true);
+                candidates.push((guard.clone(), (next_row, next_col)));
+            }
+            guard.move_forward();
+        }
+        candidates
+    }
+
+    // Returns true if, with an extra obstruction at `obstruction`, the
+    // guard starting out as `guard` never leaves the lab. The guard is
+    // looping as soon as it's back in a cell facing a way it has faced there
+    // before. `seen` is scratch space, left empty again afterwards.
+    pub fn loops(
+        &self,
+        seen: &mut DirectionGrid,
+        mut guard: Guard,
+        obstruction: (usize, usize),
+    ) -> bool {
+        let looped = loop {
+            if !seen.insert(guard.row, guard.col, guard.direction) {
+                break true;
+            }
+            let Some((next_row, next_col)) = self.ahead(&guard) else {
+                break false;
+            };
+            if (next_row, next_col) == obstruction || *self.obstructions.get(next_row, next_col) {
+                guard.turn();
+            } else {
+                guard.move_forward();
+            }
+        };
+        seen.clear();
+        looped
+    }
+
+    // Does the same as `loops`, but jumps from turn to turn using `table`,
+    // which gets the obstruction patched in for the duration. Only the
+    // places the guard turns need remembering to spot a loop.
+    pub fn loops_with_jumps(
+        &self,
+        table: &mut JumpTable,
+        seen: &mut DirectionGrid,
+        mut guard: Guard,
+        obstruction: (usize, usize),
+    ) -> bool {
+        let changes = table.block(self, obstruction);
+        let looped = loop {
+            let Some((row, col)) = table.stop(&guard) else {
+                break false;
+            };
+            guard.row = row;
+            guard.col = col;
+            if !seen.insert(row, col, guard.direction) {
+                break true;
+            }
+            guard.turn();
+        };
+        table.undo(changes);
+        seen.clear();
+        looped
+    }
+
+    // Returns the cells where one more obstruction would trap the guard in
+    // a loop, checking the candidates on every core, each with its own
+    // copy of the jump table and scratch grid.
+    pub fn loop_obstructions(&self) -> Vec<(usize, usize)> {
+        let scratch = (
+            JumpTable::new(self),
+            DirectionGrid::new(self.width, self.height),
+        );
+        self.candidates()
+            .into_par_iter()
+            .map_with(scratch, |(table, seen), (guard, obstruction)| {
+                self.loops_with_jumps(table, seen, guard, obstruction)
+                    .then_some(obstruction)
+            })
+            .flatten()
+            .collect()
+    }
+
+    // The same count, trying an obstruction in every free cell and running
+    // the guard from the start each time.
+    pub fn brute_force_loop_obstructions(&self) -> usize {
+        let mut seen = DirectionGrid::new(self.width, self.height);
+        let mut permutations = 0;
+        for col in 0..self.height {
+            for row in 0..self.width {
+                let on_guards_current_spot = row == self.guard.row && col == self.guard.col;
+                if on_guards_current_spot || *self.obstructions.get(row, col) {
+                    continue;
+                }
+                if self.loops(&mut seen, self.guard.clone(), (row, col)) {
+                    permutations += 1;
+                }
+            }
+        }
+        permutations
+    }
+}
+
+// For every cell and direction, the cell where a guard walking straight
+// from there next has to turn, or None if it walks out of the lab first.
+#[derive(Debug, Clone)]
+pub struct JumpTable {
+    stops: Vec<Option<(usize, usize)>>,
+    height: usize,
+}
+
+impl JumpTable {
+    pub fn new(lab: &Lab) -> Self {
+        let mut table = Self {
+            stops: vec![None; lab.width * lab.height * 4],
+            height: lab.height,
+        };
+        let (width, height) = (lab.width, lab.height);
+        for direction in [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ] {
+            // Visit each cell after the one in front of it, so its stop is
+            // either right here or wherever the next cell stops.
+            let cells: Vec<(usize, usize)> = match direction {
+                Direction::Up => (0..height)
+                    .flat_map(|col| (0..width).map(move |row| (row, col)))
+                    .collect(),
+                Direction::Down => (0..height)
+                    .rev()
+                    .flat_map(|col| (0..width).map(move |row| (row, col)))
+                    .collect(),
+                Direction::Left => (0..width)
+                    .flat_map(|row| (0..height).map(move |col| (row, col)))
+                    .collect(),
+                Direction::Right => (0..width)
+                    .rev()
+                    .flat_map(|row| (0..height).map(move |col| (row, col)))
+                    .collect(),
+            };
+            for (row, col) in cells {
+                let guard = Guard {
+                    row,
+                    col,
+                    direction,
+                };
+                let stop = match lab.ahead(&guard) {
+                    None => None,
+                    Some((next_row, next_col)) if *lab.obstructions.get(next_row, next_col) => {
+                        Some((row, col))
+                    }
+                    Some((next_row, next_col)) => {
+                        table.stops[table.index(next_row, next_col, direction)]
+                    }
+                };
+                let index = table.index(row, col, direction);
+                table.stops[index] = stop;
+            }
+        }
+        table
+    }
+
+    fn index(&self, row: usize, col: usize, direction: Direction) -> usize {
+        (row * self.height + col) * 4 + direction as usize
+    }
+
+    // Returns where `guard` will next have to turn.
+    fn stop(&self, guard: &Guard) -> Option<(usize, usize)> {
+        self.stops[self.index(guard.row, guard.col, guard.direction)]
+    }
+
+    // Adds an obstruction at `cell`. Only the cells leading straight up to
+    // it change, so they're the only ones patched, and the old values are
+    // returned for `undo`.
+    fn block(&mut self, lab: &Lab, cell: (usize, usize)) -> Vec<(usize, Option<(usize, usize)>)> {
+        let mut changes = Vec::new();
+        for direction in [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ] {
+            // Walk away from the obstruction, against `direction`.
+            let mut back = Guard {
+                row: cell.0,
+                col: cell.1,
+                direction,
+            };
+            back.turn();
+            back.turn();
+            let Some(stop) = lab.ahead(&back) else {
+                continue;
+            };
+            while let Some((row, col)) = lab.ahead(&back) {
+                if *lab.obstructions.get(row, col) {
+                    break;
+                }
+                let index = self.index(row, col, direction);
+                changes.push((index, self.stops[index]));
+                self.stops[index] = Some(stop);
+                back.move_forward();
+            }
+        }
+        changes
+    }
+
+    // Takes back the changes from `block`.
+    fn undo(&mut self, changes: Vec<(usize, Option<(usize, usize)>)>) {
+        for (index, stop) in changes.into_iter().rev() {
+            self.stops[index] = stop;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+#[derive(Debug, Clone)]
+pub struct Guard {
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+}
+
+impl Guard {
+    fn new(row: usize, col: usize) -> Self {
+        Self {
+            row,
+            col,
+            direction: Direction::Up,
+        }
+    }
+
+    fn next(&self) -> Option<(usize, usize)> {
+        match self.direction {
+            Direction::Up => {
+                if self.col == 0 {
+                    None
+                } else {
+                    Some((self.row, self.col - 1))
+                }
+            }
+            Direction::Right => Some((self.row + 1, self.col)),
+            Direction::Down => Some((self.row, self.col + 1)),
+            Direction::Left => {
+                if self.row == 0 {
+                    None
+                } else {
+                    Some((self.row - 1, self.col))
+                }
+            }
+        }
+    }
+
+    fn move_forward(&mut self) {
+        let (row, col) = self.next().expect("move should be valid");
+        self.row = row;
+        self.col = col;
+    }
+
+    fn turn(&mut self) {
+        self.direction = match self.direction {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[allow(unused)]
+pub struct PositionMap<T: Clone + Default + PartialEq> {
+    positions: Vec<T>,
+    rows: usize,
+    cols: usize,
+}
+
+impl<T> PositionMap<T>
+where
+    T: Clone + Default + PartialEq,
+{
+    fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            positions: vec![T::default(); rows * cols],
+            rows,
+            cols,
+        }
+    }
+
+    // Set a position in the map as visited/occupied.
+    fn set(&mut self, row: usize, col: usize, val: T) {
+        self.positions[row * self.cols + col] = val;
+    }
+
+    // Return a reference to the value at the requested position.
+    fn get(&self, row: usize, col: usize) -> &T {
+        &self.positions[row * self.cols + col]
+    }
+
+    // Count all visited/occupied positions in the map.
+    pub fn count(&self) -> usize {
+        let default = T::default();
+        self.positions.iter().filter(|&pos| *pos != default).count()
+    }
+}
+
+// A set of (cell, direction) pairs, with four bits per cell packed two
+// cells to a byte. That's small enough to wipe between simulations rather
+// than allocate a new one.
+#[derive(Debug, Clone)]
+pub struct DirectionGrid {
+    bits: Vec<u8>,
+    height: usize,
+}
+
+impl DirectionGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            bits: vec![0; (width * height).div_ceil(2)],
+            height,
+        }
+    }
+
+    // Adds `direction` at a cell, returning false if it was already there.
+    fn insert(&mut self, row: usize, col: usize, direction: Direction) -> bool {
+        let cell = row * self.height + col;
+        let bit = 1 << (direction as usize + 4 * (cell % 2));
+        let byte = &mut self.bits[cell / 2];
+        if *byte & bit != 0 {
+            return false;
+        }
+        *byte |= bit;
+        true
+    }
+
+    // Empties the set.
+    fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_against_brute_force(input: &str, expected: usize) {
+        let lab: Lab = input.parse().unwrap();
+        assert_eq!(lab.brute_force_loop_obstructions(), expected);
+        assert_eq!(lab.loop_obstructions().len(), expected);
+    }
+
+    #[test]
+    fn loop_obstructions_match_brute_force_on_example() {
+        check_against_brute_force(include_str!("bin/day6/example.txt"), 6);
+    }
+
+    #[test]
+    fn loop_obstructions_match_brute_force_on_input() {
+        check_against_brute_force(include_str!("bin/day6/input.txt"), 1748);
+    }
+}
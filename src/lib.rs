@@ -0,0 +1,2 @@
+// Code shared between the day binaries and the `aoc2024` launcher.
+pub mod day6;
@@ -1,3 +1,31 @@
+use std::{env, process};
+
+use aoc2024::day6;
+
 fn main() {
-    println!("Welcome to AOC 2024! Use `cargo run --bin dayN` to run a specific day's challenge.");
+    let args: Vec<String> = env::args().collect();
+    match (
+        args.get(1).map(String::as_str),
+        args.get(2).map(String::as_str),
+    ) {
+        // `cargo run -- viz 6 [options]`; see day6::viz. The input defaults to
+        // day 6's own, so this works from anywhere.
+        (Some("viz"), Some("6")) => {
+            let input = concat!(env!("CARGO_MANIFEST_DIR"), "/src/bin/day6/input.txt");
+            if let Err(err) = day6::viz(&args[3..], input) {
+                eprintln!("error: {err:#}");
+                process::exit(1);
+            }
+        }
+        (Some("viz"), day) => {
+            eprintln!("no visualization for day {}", day.unwrap_or("?"));
+            process::exit(1);
+        }
+        _ => {
+            println!(
+                "Welcome to AOC 2024! Use `cargo run --bin dayN` to run a specific day's challenge."
+            );
+            println!("Use `cargo run -- viz 6` to watch day 6's guard on patrol.");
+        }
+    }
 }